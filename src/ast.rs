@@ -3,6 +3,7 @@ use thiserror::Error;
 
 use self::statement::Statement;
 
+mod check;
 pub mod expression;
 pub mod identifier;
 pub mod statement;
@@ -17,6 +18,16 @@ pub enum AstError {
   MissingStatement(Location),
   #[error("missing identifier")]
   MissingIdentifier(Location),
+  #[error("'break' outside of a loop")]
+  BreakOutsideLoop(Location),
+  #[error("'continue' outside of a loop")]
+  ContinueOutsideLoop(Location),
+  #[error("invalid pattern, {0}")]
+  InvalidPattern(String, Location),
+  #[error("match doesn't cover {0}")]
+  NonExhaustiveMatch(String, Location),
+  #[error("'{0}' is a constant and can't be reassigned")]
+  AssignToConstant(String, Location),
 }
 pub type AstResult<T> = Result<T, AstError>;
 
@@ -26,7 +37,12 @@ impl AstError {
       AstError::TokenError(TokenError { location, .. })
       | AstError::MissingExpression(location)
       | AstError::MissingStatement(location)
-      | AstError::MissingIdentifier(location) => *location,
+      | AstError::MissingIdentifier(location)
+      | AstError::BreakOutsideLoop(location)
+      | AstError::ContinueOutsideLoop(location)
+      | AstError::InvalidPattern(_, location)
+      | AstError::NonExhaustiveMatch(_, location)
+      | AstError::AssignToConstant(_, location) => *location,
     }
   }
 }
@@ -39,13 +55,10 @@ pub struct Ast<'a> {
 impl<'a> Ast<'a> {
   pub fn new(tokens: &mut TokenStream<'a>) -> AstResult<Self> {
     let mut statements = Vec::new();
-    loop {
-      if let Some(statement) = Statement::try_statement_opt(tokens)? {
-        statements.push(statement)
-      } else {
-        break;
-      }
+    while let Some(statement) = Statement::try_statement_opt(tokens)? {
+      statements.push(statement)
     }
+    check::check(&statements)?;
 
     Ok(Ast { statements })
   }
@@ -53,7 +66,7 @@ impl<'a> Ast<'a> {
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub struct Location {
-  /// `None` if end of file
+  /// Byte offset into the source, `None` if end of file
   pub position: Option<usize>,
 }
 
@@ -64,14 +77,13 @@ impl Location {
 
   pub fn description(&self, file: &str) -> String {
     if let Some(position) = self.position {
-      let mut n = 0;
-      for (l, line) in file.lines().enumerate() {
-        for (c, _) in line.chars().chain(std::iter::once('\n')).enumerate() {
-          n += 1; // increment 1 for line ending
-          if n == position {
-            return format!("line {}, col {}", l + 1, c + 1);
-          }
+      let mut line_start = 0;
+      for (l, line) in file.split_inclusive('\n').enumerate() {
+        if position < line_start + line.len() {
+          let column = line[..position - line_start].chars().count();
+          return format!("line {}, col {}", l + 1, column + 1);
         }
+        line_start += line.len();
       }
       unreachable!()
     } else {
@@ -79,3 +91,222 @@ impl Location {
     }
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::{expression::Expression, *};
+  use crate::{
+    test_util::SHAPE,
+    token::{Grammar, TokenErrorKind},
+  };
+
+  #[test]
+  fn function_docs() {
+    let mut tokens = TokenStream::new(
+      "{
+        1
+      }(a) documented fn
+      Does something \\\\\\
+      across lines \\\\\\
+
+      {}()undocumented fn",
+    )
+    .unwrap();
+    let ast = Ast::new(&mut tokens).unwrap();
+    let docs: Vec<_> = ast
+      .statements
+      .iter()
+      .map(|statement| match statement {
+        Statement::Function(function) => function.docs.clone(),
+        _ => panic!("expected function"),
+      })
+      .collect();
+    assert_eq!(docs, vec![None, Some("Does something\nacross lines".to_string())]);
+  }
+
+  fn parse_error(code: &str) -> AstError {
+    let mut tokens = TokenStream::new(code).unwrap();
+    Ast::new(&mut tokens).unwrap_err()
+  }
+
+  #[test]
+  fn unclosed_block() {
+    assert_eq!(
+      parse_error("\n  1\n} true if"),
+      AstError::TokenError(TokenError {
+        kind: TokenErrorKind::UnclosedGrammar(Grammar::CloseCurly),
+        location: Location::new(Some(5)),
+      })
+    );
+  }
+
+  #[test]
+  fn unclosed_brackets() {
+    assert_eq!(
+      parse_error("1, 2)print"),
+      AstError::TokenError(TokenError {
+        kind: TokenErrorKind::UnclosedGrammar(Grammar::CloseBracket),
+        location: Location::new(Some(4)),
+      })
+    );
+    assert_eq!(
+      parse_error("1)print"),
+      AstError::TokenError(TokenError {
+        kind: TokenErrorKind::UnclosedGrammar(Grammar::CloseBracket),
+        location: Location::new(Some(1)),
+      })
+    );
+    assert_eq!(
+      parse_error("1 + 2) = a"),
+      AstError::TokenError(TokenError {
+        kind: TokenErrorKind::UnclosedGrammar(Grammar::CloseBracket),
+        location: Location::new(Some(5)),
+      })
+    );
+    assert_eq!(
+      parse_error("a)f fn"),
+      AstError::TokenError(TokenError {
+        kind: TokenErrorKind::UnclosedGrammar(Grammar::CloseBracket),
+        location: Location::new(Some(1)),
+      })
+    );
+  }
+
+  #[test]
+  fn minus_starting_line() {
+    let statements = |code| Ast::new(&mut TokenStream::new(code).unwrap()).unwrap().statements.len();
+    // statements aren't delimited, so a `-` starting a line negates rather than subtracting from the line above
+    assert_eq!(statements("(y)print\n-1 = y\n(5)print"), 3);
+    assert_eq!(statements("y\n  -x = y"), 2);
+    assert_eq!(statements("y -x = y"), 1);
+    assert_eq!(statements("y -\n  x = y"), 1);
+  }
+
+  #[test]
+  fn location_description() {
+    let file = "\"héllo\" = a\n(a)print";
+    assert_eq!(Location::new(Some(0)).description(file), "line 1, col 1");
+    assert_eq!(Location::new(Some(11)).description(file), "line 1, col 11");
+    assert_eq!(Location::new(Some(14)).description(file), "line 2, col 2");
+    assert_eq!(Location::new(None).description(file), "end of file");
+  }
+
+  #[test]
+  fn loop_control_outside_loop() {
+    assert_eq!(parse_error("break"), AstError::BreakOutsideLoop(Location::new(Some(0))));
+    assert_eq!(
+      parse_error("{ continue } true if"),
+      AstError::ContinueOutsideLoop(Location::new(Some(2)))
+    );
+    // functions don't inherit the loop they're declared in
+    assert_eq!(
+      parse_error("{ { break }() f fn } true while"),
+      AstError::BreakOutsideLoop(Location::new(Some(4)))
+    );
+    let mut tokens = TokenStream::new("{ { continue } else { break } a if } true while").unwrap();
+    assert!(Ast::new(&mut tokens).is_ok());
+    assert_eq!(
+      parse_error("{ { break }() fn = f } true while"),
+      AstError::BreakOutsideLoop(Location::new(Some(4)))
+    );
+  }
+
+  #[test]
+  fn assign_to_constant() {
+    let constant = |name: &str, position| AstError::AssignToConstant(name.to_string(), Location::new(Some(position)));
+    assert_eq!(parse_error("2 = x\n1 = x const"), constant("x", 4));
+    assert_eq!(parse_error("{ 2 = x } true if\n1 = x const"), constant("x", 6));
+    assert_eq!(parse_error("9 = [0]xs\n[1] = xs const"), constant("xs", 7));
+    assert_eq!(parse_error("1 = x let\n1 = x const"), constant("x", 4));
+    assert_eq!(parse_error("1 = f\n{}() f fn"), constant("f", 4));
+
+    for code in [
+      "{ 2 = x let } true if\n1 = x const",
+      "1 = f\n{}() f fn mut",
+      // left to the interpreter, as `x` could be shadowed by the time `f` is called
+      "{ 2 = x }() f fn\n1 = x const",
+    ] {
+      let mut tokens = TokenStream::new(code).unwrap();
+      assert!(Ast::new(&mut tokens).is_ok(), "{code}");
+    }
+  }
+
+  #[test]
+  fn hoisted_constant() {
+    assert_eq!(
+      parse_error("{}() f fn\n1 = f"),
+      AstError::AssignToConstant("f".to_string(), Location::new(Some(14)))
+    );
+    assert_eq!(
+      parse_error("{}() f fn\n1 = f let"),
+      AstError::AssignToConstant("f".to_string(), Location::new(Some(14)))
+    );
+  }
+
+  #[test]
+  fn anonymous_function() {
+    let mut tokens = TokenStream::new("{ x }(x) fn\n{ x }(x) f fn").unwrap();
+    let ast = Ast::new(&mut tokens).unwrap();
+    assert!(matches!(
+      &ast.statements[..],
+      [Statement::Function(_), Statement::Expression(Expression::Function(_))]
+    ));
+  }
+
+  #[test]
+  fn match_exhaustive() {
+    for code in [
+      format!("{{ {{}} (r)Circle::Shape {{}} (w, h)Rect::Shape {{}} Empty::Shape }} s match\n{SHAPE}"),
+      format!("{{ {{}} _ {{}} Empty::Shape }} s match\n{SHAPE}"),
+    ] {
+      let mut tokens = TokenStream::new(&code).unwrap();
+      assert!(Ast::new(&mut tokens).is_ok());
+    }
+
+    assert_eq!(
+      parse_error(&format!("{{ {{}} (w, h)Rect::Shape }} s match\n{SHAPE}")),
+      AstError::NonExhaustiveMatch("'Shape::Empty', 'Shape::Circle'".to_string(), Location::new(Some(25)))
+    );
+    assert_eq!(
+      parse_error("{} s match"),
+      AstError::NonExhaustiveMatch("'_'".to_string(), Location::new(Some(3)))
+    );
+  }
+
+  #[test]
+  fn match_invalid_pattern() {
+    assert!(matches!(
+      parse_error(&format!("{{ {{}} (r, x)Circle::Shape {{}} _ }} s match\n{SHAPE}")),
+      AstError::InvalidPattern(..)
+    ));
+    assert!(matches!(
+      parse_error(&format!("{{ {{}} Square::Shape {{}} _ }} s match\n{SHAPE}")),
+      AstError::InvalidPattern(..)
+    ));
+    assert!(matches!(
+      parse_error("{ {} Empty::Shape } s match"),
+      AstError::InvalidPattern(..)
+    ));
+  }
+
+  #[test]
+  fn match_shadowed_enum() {
+    let shadowed = |outer_match: &str| {
+      format!(
+        "{outer_match}
+        {{
+          {{ {{}} (x)Square::Shape }} s match
+          {{ (x)Square }} Shape enum
+        }} true if
+        {SHAPE}"
+      )
+    };
+    let outer = shadowed("{ {} Empty::Shape {} (w, h)Rect::Shape {} (r)Circle::Shape } s match");
+    assert!(Ast::new(&mut TokenStream::new(&outer).unwrap()).is_ok());
+    // the inner enum isn't visible outside its block
+    assert!(matches!(
+      parse_error(&shadowed("{ {} (x)Square::Shape {} _ } s match")),
+      AstError::InvalidPattern(..)
+    ));
+  }
+}
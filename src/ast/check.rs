@@ -0,0 +1,308 @@
+//! Checks which need the context of the surrounding statements, made once the whole tree is parsed
+
+use std::collections::HashMap;
+
+use crate::token::Operator;
+
+use super::{
+  expression::Expression,
+  identifier::Identifier,
+  statement::{
+    enumeration::Enum,
+    for_loop::Iterable,
+    function::Function,
+    matching::{Match, Pattern},
+    Statement,
+  },
+  AstError, AstResult, Location,
+};
+
+/// The names declared in a block, and whether each is a constant
+#[derive(Default)]
+struct Scope<'s, 'a> {
+  names: HashMap<Identifier<'a>, bool>,
+  /// The enums declared in the block, which patterns anywhere within it can refer to
+  enums: HashMap<Identifier<'a>, &'s Enum<'a>>,
+  /// The outermost scope of a function, holding its arguments
+  function: bool,
+}
+
+struct Checker<'s, 'a> {
+  in_loop: bool,
+  scopes: Vec<Scope<'s, 'a>>,
+}
+
+/// Rejects `break` and `continue` outside of a loop, where a loop doesn't extend into functions declared or written
+/// within it, invalid or non-exhaustive matches, and reassigning constants where it's known they will be.
+pub(super) fn check(statements: &[Statement]) -> AstResult<()> {
+  let mut checker = Checker {
+    in_loop: false,
+    scopes: vec![Scope::default()],
+  };
+  checker.block(statements)
+}
+
+impl<'s, 'a> Checker<'s, 'a> {
+  /// Functions are declared before the rest of the block, as they are when it runs. So are enums, as functions in
+  /// the block can match on them.
+  fn block(&mut self, statements: &'s [Statement<'a>]) -> AstResult<()> {
+    for statement in statements {
+      match statement {
+        Statement::Function(Function {
+          name: Some(name),
+          mutable,
+          location,
+          ..
+        }) => self.declare(*name, !mutable, *location)?,
+        Statement::Enum(enumeration) => {
+          self
+            .scopes
+            .last_mut()
+            .unwrap()
+            .enums
+            .insert(enumeration.name, enumeration);
+        }
+        _ => (),
+      }
+    }
+    statements.iter().try_for_each(|statement| self.statement(statement))
+  }
+
+  fn statement(&mut self, statement: &'s Statement<'a>) -> AstResult<()> {
+    match statement {
+      Statement::Break(location) if !self.in_loop => Err(AstError::BreakOutsideLoop(*location)),
+      Statement::Continue(location) if !self.in_loop => Err(AstError::ContinueOutsideLoop(*location)),
+      Statement::Expression(expression) | Statement::Return(expression) | Statement::Defer(expression) => {
+        self.expression(expression)
+      }
+      Statement::Declaration(declaration) => {
+        if let Some(value) = &declaration.value {
+          self.expression(value)?;
+        }
+        self.declare(declaration.name, declaration.constant, declaration.location)
+      }
+      Statement::Conditional(conditional) => {
+        for branch in &conditional.branches {
+          self.expression(&branch.condition)?;
+          self.nested(self.in_loop, &branch.block)?;
+        }
+        self.nested(self.in_loop, &conditional.else_block)
+      }
+      Statement::Match(matching) => {
+        self.expression(&matching.value)?;
+        self.matching(matching)
+      }
+      Statement::While(while_loop) => {
+        self.expression(&while_loop.condition)?;
+        self.nested(true, &while_loop.block)
+      }
+      Statement::For(for_loop) => {
+        for expression in expressions(statement) {
+          self.expression(expression)?;
+        }
+        self.scopes.push(Scope::default());
+        self.declare(for_loop.variable, false, for_loop.location)?;
+        let result = self.nested(true, &for_loop.block);
+        self.scopes.pop();
+        result
+      }
+      Statement::Function(function) => self.function(function),
+      Statement::Record(record) => self.declare(record.name, false, record.location),
+      Statement::Enum(enumeration) => self.declare(enumeration.name, false, enumeration.location),
+      Statement::Break(_) | Statement::Continue(_) => Ok(()),
+    }
+  }
+
+  /// A block with its own scope
+  fn nested(&mut self, in_loop: bool, statements: &'s [Statement<'a>]) -> AstResult<()> {
+    let outer = std::mem::replace(&mut self.in_loop, in_loop);
+    self.scopes.push(Scope::default());
+    let result = self.block(statements);
+    self.scopes.pop();
+    self.in_loop = outer;
+    result
+  }
+
+  fn function(&mut self, function: &'s Function<'a>) -> AstResult<()> {
+    self.scopes.push(Scope {
+      names: function.arguments.iter().map(|argument| (*argument, false)).collect(),
+      function: true,
+      ..Scope::default()
+    });
+    let result = self.nested(false, &function.block);
+    self.scopes.pop();
+    result
+  }
+
+  fn expression(&mut self, expression: &'s Expression<'a>) -> AstResult<()> {
+    match expression {
+      Expression::Operation {
+        operator: Operator::Assign,
+        left,
+        right,
+        ..
+      } => {
+        self.expression(left)?;
+        self.target(right, true)
+      }
+      Expression::Function(function) => self.function(function),
+      expression => children(expression)
+        .into_iter()
+        .try_for_each(|child| self.expression(child)),
+    }
+  }
+
+  /// What's assigned to, where only a variable being assigned directly is declared if it doesn't exist
+  fn target(&mut self, target: &'s Expression<'a>, declares: bool) -> AstResult<()> {
+    match target {
+      Expression::Identifier(name, location) => self.assign(*name, *location, declares),
+      Expression::Index { expression, index, .. } => {
+        self.expression(index)?;
+        self.target(expression, false)
+      }
+      Expression::Field { expression, .. } => self.target(expression, false),
+      target => self.expression(target),
+    }
+  }
+
+  fn declare(&mut self, name: Identifier<'a>, constant: bool, location: Location) -> AstResult<()> {
+    let scope = self.scopes.last_mut().unwrap();
+    if scope.names.get(&name) == Some(&true) {
+      return Err(AstError::AssignToConstant(name.to_string(), location));
+    }
+    scope.names.insert(name, constant);
+    Ok(())
+  }
+
+  fn assign(&mut self, name: Identifier<'a>, location: Location, declares: bool) -> AstResult<()> {
+    for scope in self.scopes.iter().rev() {
+      match scope.names.get(&name) {
+        Some(true) => return Err(AstError::AssignToConstant(name.to_string(), location)),
+        Some(false) => return Ok(()),
+        // by the time a function is called, what's outside it may have been shadowed, so leave those to the
+        // interpreter
+        None if scope.function => return Ok(()),
+        None => (),
+      }
+    }
+    if declares {
+      self.scopes.last_mut().unwrap().names.insert(name, false);
+    }
+    Ok(())
+  }
+
+  /// Every pattern must be a variant of the same declared enum, and together they must cover every variant
+  fn matching(&mut self, matching: &'s Match<'a>) -> AstResult<()> {
+    let mut enumeration: Option<&Enum> = None;
+    let mut covered = Vec::new();
+    let mut wildcard = false;
+    for arm in &matching.arms {
+      match &arm.pattern {
+        Pattern::Wildcard => wildcard = true,
+        Pattern::Variant {
+          enumeration: name,
+          variant,
+          bindings,
+        } => {
+          let invalid = |reason: String| AstError::InvalidPattern(reason, arm.location);
+          let declared = self
+            .scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.enums.get(name).copied())
+            .ok_or_else(|| invalid(format!("unknown enum '{name}'")))?;
+          match enumeration {
+            Some(enumeration) if enumeration.name != *name => {
+              return Err(invalid(format!("expected a variant of '{}'", enumeration.name)));
+            }
+            _ => enumeration = Some(declared),
+          }
+
+          let fields = &declared
+            .variant(variant)
+            .ok_or_else(|| invalid(format!("'{name}' has no variant '{variant}'")))?
+            .fields;
+          if fields.len() != bindings.len() {
+            return Err(invalid(format!(
+              "'{name}::{variant}' has {} field(s), found {}",
+              fields.len(),
+              bindings.len()
+            )));
+          }
+          covered.push(*variant);
+        }
+      }
+      self.scopes.push(Scope::default());
+      if let Pattern::Variant { bindings, .. } = &arm.pattern {
+        for binding in bindings {
+          self.declare(*binding, false, arm.location)?;
+        }
+      }
+      let result = self.nested(self.in_loop, &arm.block);
+      self.scopes.pop();
+      result?;
+    }
+
+    if wildcard {
+      return Ok(());
+    }
+    let missing: Vec<_> = match enumeration {
+      Some(enumeration) => enumeration
+        .variants
+        .iter()
+        .filter(|variant| !covered.contains(&variant.name))
+        .map(|variant| format!("'{}::{}'", enumeration.name, variant.name))
+        .collect(),
+      None => vec!["'_'".to_string()],
+    };
+    if missing.is_empty() {
+      Ok(())
+    } else {
+      Err(AstError::NonExhaustiveMatch(missing.join(", "), matching.location))
+    }
+  }
+}
+
+/// The expressions a statement evaluates itself, not including those within its blocks
+fn expressions<'s, 'a>(statement: &'s Statement<'a>) -> Vec<&'s Expression<'a>> {
+  match statement {
+    Statement::Expression(expression) | Statement::Return(expression) | Statement::Defer(expression) => {
+      vec![expression]
+    }
+    Statement::Conditional(conditional) => conditional.branches.iter().map(|branch| &branch.condition).collect(),
+    Statement::Declaration(declaration) => declaration.value.iter().collect(),
+    Statement::Match(matching) => vec![&matching.value],
+    Statement::While(while_loop) => vec![&while_loop.condition],
+    Statement::For(for_loop) => match &for_loop.iterable {
+      Iterable::Range { start, end, step } => [Some(start), Some(end), step.as_ref()].into_iter().flatten().collect(),
+      Iterable::Expression(expression) => vec![expression],
+    },
+    Statement::Function(_)
+    | Statement::Record(_)
+    | Statement::Enum(_)
+    | Statement::Break(_)
+    | Statement::Continue(_) => Vec::new(),
+  }
+}
+
+/// The expressions directly within an expression, in the order they're evaluated
+fn children<'s, 'a>(expression: &'s Expression<'a>) -> Vec<&'s Expression<'a>> {
+  match expression {
+    Expression::Operation { left, right, .. } => vec![left, right],
+    Expression::Unary { expression, .. }
+    | Expression::Brackets(expression, _)
+    | Expression::Field { expression, .. } => vec![expression],
+    Expression::Index { expression, index, .. } => vec![expression, index],
+    Expression::Call {
+      function, arguments, ..
+    } => std::iter::once(&**function).chain(arguments).collect(),
+    Expression::Variant { arguments, .. } | Expression::List(arguments, _) => arguments.iter().collect(),
+    Expression::Map(entries, _) => entries.iter().flat_map(|(key, value)| [key, value]).collect(),
+    Expression::Construct { fields, .. } => fields.iter().map(|(_, value)| value).collect(),
+    Expression::Bool(..)
+    | Expression::String(..)
+    | Expression::Number(..)
+    | Expression::Identifier(..)
+    | Expression::Function(_) => Vec::new(),
+  }
+}
@@ -1,14 +1,15 @@
 use crate::{
+  cst::SyntaxKind,
   interpreter::{RuntimeError, RuntimeResult},
-  token::{Grammar, Keyword, Operator, TokenStream},
+  token::{Associativity, Grammar, Keyword, Operator, TokenStream},
 };
 
-use super::{identifier::Identifier, AstError, AstResult, Location};
+use super::{identifier::Identifier, statement::function::Function, AstError, AstResult, Location};
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Expression<'a> {
   Bool(bool, Location),
-  String(&'a str, Location), // TODO: given we want to reverse, maybe use owned?
+  String(String, Location),
   Number(f64, Location),
   Operation {
     operator: Operator,
@@ -16,74 +17,255 @@ pub enum Expression<'a> {
     right: Box<Expression<'a>>,
     location: Location,
   },
+  /// A `!` or `-` applied to the expression before it in the file
+  Unary {
+    operator: Operator,
+    expression: Box<Expression<'a>>,
+    location: Location,
+  },
+  /// `f(a, b)`, which is written `(b, a)f`. Arguments are in reading order.
   Call {
-    function: Identifier<'a>,
+    function: Box<Expression<'a>>,
     arguments: Vec<Expression<'a>>,
     location: Location,
   },
   Identifier(Identifier<'a>, Location),
   Brackets(Box<Expression<'a>>, Location),
+  /// Elements are in the order they're written, i.e. the reverse of reading order
+  List(Vec<Expression<'a>>, Location),
+  /// `{"key": value}` entries, in the order they're written
+  Map(Vec<(Expression<'a>, Expression<'a>)>, Location),
+  /// `new Point { x: 1 }`, which is written `{ x: 1 } Point new`. Fields are in the order they're written.
+  Construct {
+    record: Identifier<'a>,
+    fields: Vec<(Identifier<'a>, Expression<'a>)>,
+    location: Location,
+  },
+  /// `Shape::Circle(r)`, which is written `(r)Circle::Shape`. Arguments are in the order they're written.
+  Variant {
+    enumeration: Identifier<'a>,
+    variant: Identifier<'a>,
+    arguments: Vec<Expression<'a>>,
+    location: Location,
+  },
+  /// `p.x`, which is written `x.p`
+  Field {
+    expression: Box<Expression<'a>>,
+    field: Identifier<'a>,
+    location: Location,
+  },
+  /// `x[i]`, which is written `[i]x`
+  Index {
+    expression: Box<Expression<'a>>,
+    index: Box<Expression<'a>>,
+    location: Location,
+  },
+  /// An anonymous function, written `{ ... }(a, b) fn`
+  Function(Box<Function<'a>>),
 }
 
 impl<'a> Expression<'a> {
   pub fn try_expression(tokens: &mut TokenStream<'a>) -> AstResult<Self> {
-    // TODO: maybe allow for backets?
-    let right = if tokens.try_keyword(Keyword::True).is_ok() {
-      Expression::Bool(true, tokens.location())
+    let start = tokens.start_node();
+    let expression = Expression::try_operation(tokens, 0)?;
+    tokens.finish_node(SyntaxKind::Expression, start);
+    Ok(expression)
+  }
+
+  /// Precedence climbing over the operators in reading order, only taking operators which bind at least as tightly as
+  /// `min_precedence`. The operand read first becomes `right`, as it is on the right in the file.
+  fn try_operation(tokens: &mut TokenStream<'a>, min_precedence: u8) -> AstResult<Self> {
+    let mut right = Expression::try_unary(tokens)?;
+
+    while let Some(operator) = tokens.peek_operator() {
+      let precedence = operator.precedence();
+      if precedence < min_precedence {
+        break;
+      }
+
+      let location = tokens.location();
+      tokens.try_operator(operator)?;
+      let next_precedence = match operator.associativity() {
+        Associativity::Left => precedence + 1,
+        Associativity::Right => precedence,
+      };
+      let left = Expression::try_operation(tokens, next_precedence)?;
+      right = Expression::Operation {
+        operator,
+        left: Box::new(left),
+        right: Box::new(right),
+        location,
+      };
+    }
+
+    Ok(right)
+  }
+
+  /// A primary followed by any unary operators, which bind tighter than all binary operators
+  fn try_unary(tokens: &mut TokenStream<'a>) -> AstResult<Self> {
+    let mut expression = Expression::try_primary(tokens)?;
+    loop {
+      let location = tokens.location();
+      let Some(operator) = tokens.try_unary_operator_opt() else {
+        return Ok(expression);
+      };
+      expression = match (operator, expression) {
+        // a negative number literal
+        (Operator::Subtract, Expression::Number(number, _)) => Expression::Number(-number, location),
+        (operator, expression) => Expression::Unary {
+          operator,
+          expression: Box::new(expression),
+          location,
+        },
+      };
+    }
+  }
+
+  /// A primary followed by any indexes, fields or calls of it
+  fn try_primary(tokens: &mut TokenStream<'a>) -> AstResult<Self> {
+    let mut expression = Expression::try_value(tokens)?;
+    loop {
+      let location = tokens.location();
+      if tokens.try_grammar(Grammar::CloseBracket).is_ok() {
+        let arguments = Expression::try_list(tokens, Grammar::CloseBracket, location)?;
+        expression = Expression::Call {
+          location: expression.location(),
+          function: Box::new(expression),
+          arguments,
+        };
+      } else if tokens.try_grammar(Grammar::Dot).is_ok() {
+        expression = Expression::Field {
+          expression: Box::new(expression),
+          field: tokens.try_identifier()?,
+          location,
+        };
+      } else if tokens.try_grammar(Grammar::CloseSquare).is_ok() {
+        tokens.check_unclosed(Grammar::CloseSquare, location)?;
+        let index = Expression::try_expression(tokens)?;
+        tokens.try_closing_grammar(Grammar::CloseSquare, location)?;
+        expression = Expression::Index {
+          expression: Box::new(expression),
+          index: Box::new(index),
+          location,
+        };
+      } else {
+        return Ok(expression);
+      }
+    }
+  }
+
+  fn try_value(tokens: &mut TokenStream<'a>) -> AstResult<Self> {
+    let location = tokens.location();
+    let expression = if tokens.try_keyword(Keyword::True).is_ok() {
+      Expression::Bool(true, location)
     } else if tokens.try_keyword(Keyword::False).is_ok() {
-      Expression::Bool(false, tokens.location())
-    } else if let Some(string) = tokens.try_string_opt()? {
-      Expression::String(string, tokens.location())
-    } else if let Some(number) = tokens.try_number_opt()? {
-      Expression::Number(number, tokens.location())
+      Expression::Bool(false, location)
+    } else if let Some(string) = tokens.try_string_opt() {
+      Expression::String(string, location)
+    } else if let Some(number) = tokens.try_number_opt() {
+      Expression::Number(number, location)
     } else if tokens.try_grammar(Grammar::CloseBracket).is_ok() {
+      tokens.check_unclosed(Grammar::CloseBracket, location)?;
       let expression = Expression::try_expression(tokens)?;
-      tokens.try_grammar(Grammar::OpenBracket)?;
-      Expression::Brackets(Box::new(expression), tokens.location())
-    } else if let Some(identifier) = tokens.try_identifier_opt()? {
-      // see if there are brackets, indicating a function call
-      if tokens.try_grammar(Grammar::CloseBracket).is_ok() {
-        let mut arguments = Vec::new();
-        loop {
-          if tokens.try_grammar(Grammar::OpenBracket).is_ok() {
-            // end of arguments
-            break;
-          }
-          arguments.push(Expression::try_expression(tokens)?);
-
-          if tokens.try_grammar(Grammar::Comma).is_err() {
-            // no comma, this must also be the end of arguments, expect an open bracket
-            tokens.try_grammar(Grammar::OpenBracket)?;
-            break;
-          }
+      tokens.try_closing_grammar(Grammar::CloseBracket, location)?;
+      Expression::Brackets(Box::new(expression), location)
+    } else if tokens.try_grammar(Grammar::CloseSquare).is_ok() {
+      let mut elements = Expression::try_list(tokens, Grammar::CloseSquare, location)?;
+      elements.reverse();
+      Expression::List(elements, location)
+    } else if tokens.try_grammar(Grammar::CloseCurly).is_ok() {
+      let mut entries = Vec::new();
+      loop {
+        tokens.check_unclosed(Grammar::CloseCurly, location)?;
+        if tokens.try_closing_grammar(Grammar::CloseCurly, location).is_ok() {
+          break;
         }
-        Expression::Call {
-          function: identifier,
+        // read backwards, so the value comes before the key
+        let value = Expression::try_expression(tokens)?;
+        tokens.try_grammar(Grammar::Colon)?;
+        let key = Expression::try_expression(tokens)?;
+        entries.push((key, value));
+
+        if tokens.try_grammar(Grammar::Comma).is_err() {
+          tokens.try_closing_grammar(Grammar::CloseCurly, location)?;
+          break;
+        }
+      }
+      entries.reverse();
+      Expression::Map(entries, location)
+    } else if let Some(function) = Function::try_anonymous_opt(tokens)? {
+      Expression::Function(Box::new(function))
+    } else if tokens.try_keyword(Keyword::New).is_ok() {
+      let record_location = tokens.location();
+      let record = tokens.try_identifier()?;
+      let curly_location = tokens.location();
+      tokens.try_grammar(Grammar::CloseCurly)?;
+      let mut fields = Vec::new();
+      loop {
+        tokens.check_unclosed(Grammar::CloseCurly, curly_location)?;
+        if tokens.try_closing_grammar(Grammar::CloseCurly, curly_location).is_ok() {
+          break;
+        }
+        // read backwards, so the value comes before the field
+        let value = Expression::try_expression(tokens)?;
+        tokens.try_grammar(Grammar::Colon)?;
+        fields.push((tokens.try_identifier()?, value));
+
+        if tokens.try_grammar(Grammar::Comma).is_err() {
+          tokens.try_closing_grammar(Grammar::CloseCurly, curly_location)?;
+          break;
+        }
+      }
+      fields.reverse();
+      Expression::Construct {
+        record,
+        fields,
+        location: record_location,
+      }
+    } else if let Some(identifier) = tokens.try_identifier_opt() {
+      if tokens.try_grammar(Grammar::PathSeparator).is_ok() {
+        let variant = tokens.try_identifier()?;
+        let bracket_location = tokens.location();
+        let mut arguments = if tokens.try_grammar(Grammar::CloseBracket).is_ok() {
+          Expression::try_list(tokens, Grammar::CloseBracket, bracket_location)?
+        } else {
+          Vec::new()
+        };
+        arguments.reverse();
+        Expression::Variant {
+          enumeration: identifier,
+          variant,
           arguments,
-          location: tokens.location(),
+          location,
         }
       } else {
-        Expression::Identifier(identifier, tokens.location())
+        Expression::Identifier(identifier, location)
       }
     } else {
-      return Err(AstError::MissingExpression(tokens.location()));
+      return Err(AstError::MissingExpression(location));
     };
 
-    // see if there's an operator
-    if let Some(operator) = Operator::operators()
-      .iter()
-      .find(|op| tokens.try_operator(**op).is_ok())
-    {
-      let left = Expression::try_expression(tokens)?;
-      Ok(Expression::Operation {
-        operator: *operator,
-        left: Box::new(left),
-        right: Box::new(right),
-        location: tokens.location(),
-      })
-    } else {
-      Ok(right)
+    Ok(expression)
+  }
+
+  /// Comma separated expressions in reading order, after the `open` grammar has been consumed
+  fn try_list(tokens: &mut TokenStream<'a>, open: Grammar, open_location: Location) -> AstResult<Vec<Self>> {
+    let mut expressions = Vec::new();
+    loop {
+      tokens.check_unclosed(open, open_location)?;
+      if tokens.try_closing_grammar(open, open_location).is_ok() {
+        // end of the list
+        break;
+      }
+      expressions.push(Expression::try_expression(tokens)?);
+
+      if tokens.try_grammar(Grammar::Comma).is_err() {
+        // no comma, this must also be the end of the list, expect the closing grammar
+        tokens.try_closing_grammar(open, open_location)?;
+        break;
+      }
     }
+    Ok(expressions)
   }
 
   pub fn location(&self) -> Location {
@@ -94,7 +276,15 @@ impl<'a> Expression<'a> {
       | Expression::String(_, location)
       | Expression::Number(_, location)
       | Expression::Operation { location, .. }
+      | Expression::Unary { location, .. }
+      | Expression::List(_, location)
+      | Expression::Map(_, location)
+      | Expression::Index { location, .. }
+      | Expression::Construct { location, .. }
+      | Expression::Variant { location, .. }
+      | Expression::Field { location, .. }
       | Expression::Call { location, .. } => *location,
+      Expression::Function(function) => function.location,
     }
   }
 
@@ -109,92 +299,254 @@ impl<'a> Expression<'a> {
   }
 }
 
-// #[cfg(test)]
-// mod tests {
-//   use super::*;
-
-//   #[test]
-//   fn bool_true() {
-//     let mut tokens = TokenStream::new("true");
-//     assert_eq!(
-//       Expression::try_expression(&mut tokens),
-//       Ok(Expression::Bool(true, Location::new(None)))
-//     );
-//   }
-
-//   #[test]
-//   fn bool_false() {
-//     let mut tokens = TokenStream::new("false");
-//     assert_eq!(
-//       Expression::try_expression(&mut tokens),
-//       Ok(Expression::Bool(false, Location::new(None)))
-//     );
-//   }
-
-//   #[test]
-//   fn brackets() {
-//     let mut tokens = TokenStream::new("(false)");
-//     assert_eq!(
-//       Expression::try_expression(&mut tokens),
-//       Ok(Expression::Brackets(
-//         Box::new(Expression::Bool(false, Location::new(None))),
-//         Location::new(None)
-//       ))
-//     );
-//   }
-
-//   #[test]
-//   fn string() {
-//     let mut tokens = TokenStream::new("\"hello there\"");
-//     assert_eq!(
-//       Expression::try_expression(&mut tokens),
-//       Ok(Expression::String("hello there"))
-//     );
-//   }
-
-//   #[test]
-//   fn number() {
-//     let mut tokens = TokenStream::new("42.24");
-//     assert_eq!(Expression::try_expression(&mut tokens), Ok(Expression::Number(42.24)));
-//   }
-
-//   #[test]
-//   fn identifier() {
-//     let mut tokens = TokenStream::new("123 my_ident");
-//     assert_eq!(
-//       Expression::try_expression(&mut tokens),
-//       Ok(Expression::Identifier(Identifier("my_ident")))
-//     );
-//   }
-
-//   #[test]
-//   fn call() {
-//     let mut tokens = TokenStream::new("(123, \"hello\")my_func");
-//     assert_eq!(
-//       Expression::try_expression(&mut tokens),
-//       Ok(Expression::Call {
-//         function: Identifier("my_func"),
-//         arguments: vec![Expression::String("hello"), Expression::Number(123.)]
-//       })
-//     );
-//   }
-
-//   #[test]
-//   fn operator() {
-//     let mut tokens = TokenStream::new("\"hello\" <= 99");
-//     assert_eq!(
-//       Expression::try_expression(&mut tokens),
-//       Ok(Expression::Operation {
-//         operator: Operator::Lte,
-//         left: Box::new(Expression::String("hello")),
-//         right: Box::new(Expression::Number(99.)),
-//       })
-//     );
-//   }
-
-//   #[test]
-//   fn operator_missing_left() {
-//     let mut tokens = TokenStream::new(" + 99");
-//     assert!(Expression::try_expression(&mut tokens).is_err(),);
-//   }
-// }
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::token::{TokenError, TokenErrorKind};
+
+  fn parse(code: &str) -> AstResult<Expression<'_>> {
+    let mut tokens = TokenStream::new(code).map_err(AstError::from)?;
+    Expression::try_expression(&mut tokens)
+  }
+
+  /// Render the grouping of operations in file order, e.g. `(a Add b)`
+  fn grouping(code: &str) -> String {
+    fn render(expression: &Expression) -> String {
+      match expression {
+        Expression::Operation {
+          operator, left, right, ..
+        } => format!("({} {operator:?} {})", render(left), render(right)),
+        Expression::Unary {
+          operator, expression, ..
+        } => format!("({operator:?} {})", render(expression)),
+        Expression::Identifier(identifier, _) => identifier.to_string(),
+        Expression::Number(number, _) => number.to_string(),
+        Expression::String(string, _) => format!("{string:?}"),
+        Expression::Brackets(expression, _) => render(expression),
+        Expression::List(elements, _) => {
+          let elements: Vec<_> = elements.iter().map(render).collect();
+          format!("[{}]", elements.join(", "))
+        }
+        Expression::Map(entries, _) => {
+          let entries: Vec<_> = entries
+            .iter()
+            .map(|(key, value)| format!("{}: {}", render(key), render(value)))
+            .collect();
+          format!("{{{}}}", entries.join(", "))
+        }
+        Expression::Construct { record, fields, .. } => {
+          let fields: Vec<_> = fields
+            .iter()
+            .map(|(field, value)| format!("{field}: {}", render(value)))
+            .collect();
+          format!("new {record} {{{}}}", fields.join(", "))
+        }
+        Expression::Variant {
+          enumeration,
+          variant,
+          arguments,
+          ..
+        } => {
+          let arguments: Vec<_> = arguments.iter().map(render).collect();
+          format!("{enumeration}::{variant}({})", arguments.join(", "))
+        }
+        Expression::Field { expression, field, .. } => format!("{}.{field}", render(expression)),
+        Expression::Index { expression, index, .. } => format!("{}[{}]", render(expression), render(index)),
+        Expression::Call {
+          function, arguments, ..
+        } => {
+          let arguments: Vec<_> = arguments.iter().map(render).collect();
+          format!("{}({})", render(function), arguments.join(", "))
+        }
+        Expression::Function(function) => {
+          let arguments: Vec<_> = function.arguments.iter().map(Identifier::to_string).collect();
+          format!("fn({})", arguments.join(", "))
+        }
+        expression => panic!("unexpected expression {expression:?}"),
+      }
+    }
+    render(&parse(code).unwrap())
+  }
+
+  #[test]
+  fn bool() {
+    assert_eq!(parse("true"), Ok(Expression::Bool(true, Location::new(Some(0)))));
+    assert_eq!(parse("false"), Ok(Expression::Bool(false, Location::new(Some(0)))));
+  }
+
+  #[test]
+  fn brackets() {
+    assert_eq!(
+      parse("(false)"),
+      Ok(Expression::Brackets(
+        Box::new(Expression::Bool(false, Location::new(Some(1)))),
+        Location::new(Some(6))
+      ))
+    );
+  }
+
+  #[test]
+  fn call() {
+    assert_eq!(
+      parse("(123, \"hello\")my_func"),
+      Ok(Expression::Call {
+        function: Box::new(Expression::Identifier(Identifier("my_func"), Location::new(Some(14)))),
+        arguments: vec![
+          Expression::String("hello".to_string(), Location::new(Some(6))),
+          Expression::Number(123., Location::new(Some(1))),
+        ],
+        location: Location::new(Some(14)),
+      })
+    );
+  }
+
+  #[test]
+  fn call_expression() {
+    assert_eq!(grouping("(1)(2)adder"), "adder(2)(1)");
+    assert_eq!(grouping("(1)[0]fs"), "fs[0](1)");
+    assert_eq!(grouping("()x.p"), "p.x()");
+    assert_eq!(grouping("x.()p"), "p().x");
+    assert_eq!(grouping("(2)({ x }(x) fn)"), "fn(x)(2)");
+    assert_eq!(grouping("()f + 1"), "(f() Add 1)");
+  }
+
+  #[test]
+  fn operator() {
+    assert_eq!(
+      parse("\"hello\" <= 99"),
+      Ok(Expression::Operation {
+        operator: Operator::Lte,
+        left: Box::new(Expression::String("hello".to_string(), Location::new(Some(0)))),
+        right: Box::new(Expression::Number(99., Location::new(Some(11)))),
+        location: Location::new(Some(8)),
+      })
+    );
+  }
+
+  #[test]
+  fn operator_missing_left() {
+    assert!(parse(" + 99").is_err());
+  }
+
+  #[test]
+  fn precedence() {
+    assert_eq!(grouping("2 * 3 + 4"), "((2 Multiply 3) Add 4)");
+    assert_eq!(grouping("4 + 2 * 3"), "(4 Add (2 Multiply 3))");
+    assert_eq!(grouping("a % 2 - b / 4"), "((a Modulo 2) Subtract (b Divide 4))");
+    assert_eq!(grouping("i * i > n"), "((i Multiply i) Gt n)");
+    assert_eq!(grouping("n <= i + 1"), "(n Lte (i Add 1))");
+    assert_eq!(grouping("1 + 2 == 2"), "((1 Add 2) Equals 2)");
+    assert_eq!(grouping("a < b == c >= d"), "((a Lt b) Equals (c Gte d))");
+    assert_eq!(grouping("a == b && c == d"), "((a Equals b) And (c Equals d))");
+    assert_eq!(grouping("a && b || c && d"), "((a And b) Or (c And d))");
+    assert_eq!(grouping("a || b = c"), "((a Or b) Assign c)");
+    assert_eq!(grouping("2 * (3 + 4)"), "(2 Multiply (3 Add 4))");
+  }
+
+  #[test]
+  fn associativity() {
+    // read right to left, so operators group from the right of the file
+    assert_eq!(grouping("a - b - c"), "(a Subtract (b Subtract c))");
+    assert_eq!(grouping("a / b * c"), "(a Divide (b Multiply c))");
+    assert_eq!(grouping("a && b && c"), "(a And (b And c))");
+    // assignment is right associative in reading order
+    assert_eq!(grouping("5 = b = a"), "((5 Assign b) Assign a)");
+  }
+
+  #[test]
+  fn unary() {
+    assert_eq!(grouping("!done"), "(Not done)");
+    assert_eq!(grouping("!!done"), "(Not (Not done))");
+    assert_eq!(grouping("-x"), "(Subtract x)");
+    assert_eq!(grouping("-(a + b)"), "(Subtract (a Add b))");
+    assert_eq!(grouping("-a * b"), "((Subtract a) Multiply b)");
+    assert_eq!(grouping("!a && b"), "((Not a) And b)");
+    assert_eq!(grouping("a * -b"), "(a Multiply (Subtract b))");
+    assert_eq!(grouping("-x = y"), "((Subtract x) Assign y)");
+  }
+
+  #[test]
+  fn unary_minus_spacing() {
+    assert_eq!(grouping("a - b"), "(a Subtract b)");
+    assert_eq!(grouping("a-b"), "(a Subtract b)");
+    assert_eq!(grouping("(a)-b"), "(a Subtract b)");
+    assert_eq!(grouping("a - -b"), "(a Subtract (Subtract b))");
+    assert_eq!(grouping("a -b"), "(a Subtract b)");
+    assert_eq!(grouping("[a, -b]"), "[a, (Subtract b)]");
+    assert_eq!(grouping("-b * a"), "((Subtract b) Multiply a)");
+    assert_eq!(grouping("5 -1 = x"), "((5 Subtract 1) Assign x)");
+    assert_eq!(grouping("[5, -1, - 0x1]"), "[5, -1, -1]");
+  }
+
+  #[test]
+  fn list() {
+    assert_eq!(grouping("[1, a, 3]"), "[1, a, 3]");
+    assert_eq!(grouping("[]"), "[]");
+    assert_eq!(grouping("[[1], [2, 3]]"), "[[1], [2, 3]]");
+    assert_eq!(grouping("[1 + 2, a]"), "[(1 Add 2), a]");
+  }
+
+  #[test]
+  fn index() {
+    assert_eq!(grouping("[0]x"), "x[0]");
+    assert_eq!(grouping("[j][i]x"), "x[i][j]");
+    assert_eq!(grouping("[0](a, b)f"), "f(b, a)[0]");
+    assert_eq!(grouping("[i + 1]x * 2"), "(x[(i Add 1)] Multiply 2)");
+    assert_eq!(grouping("-[0]x"), "(Subtract x[0])");
+    assert_eq!(grouping("[1][0, 1]"), "[0, 1][1]");
+  }
+
+  #[test]
+  fn unclosed_list() {
+    assert_eq!(
+      parse("1, 2]"),
+      Err(AstError::TokenError(TokenError {
+        kind: TokenErrorKind::UnclosedGrammar(Grammar::CloseSquare),
+        location: Location::new(Some(4)),
+      }))
+    );
+  }
+
+  #[test]
+  fn map() {
+    assert_eq!(grouping("{\"a\": 1, \"b\": x + 2}"), "{\"a\": 1, \"b\": (x Add 2)}");
+    assert_eq!(grouping("{}"), "{}");
+    assert_eq!(grouping("{k: {\"x\": [1]}}"), "{k: {\"x\": [1]}}");
+    assert_eq!(grouping("[\"b\"]{\"b\": 1}"), "{\"b\": 1}[\"b\"]");
+    assert_eq!(
+      parse("{\"a\" 1}"),
+      Err(AstError::TokenError(TokenError {
+        kind: TokenErrorKind::Expected("':'".to_string()),
+        location: Location::new(Some(1)),
+      }))
+    );
+  }
+
+  #[test]
+  fn record() {
+    assert_eq!(
+      grouping("{ x: 1, y: a + 2 } Point new"),
+      "new Point {x: 1, y: (a Add 2)}"
+    );
+    assert_eq!(grouping("{} Unit new"), "new Unit {}");
+    assert_eq!(grouping("x.p"), "p.x");
+    assert_eq!(grouping("b.a.p * 2"), "(p.a.b Multiply 2)");
+    assert_eq!(grouping("[0]x.p"), "p.x[0]");
+    assert_eq!(grouping("x.[0]p"), "p[0].x");
+    assert_eq!(grouping("x.{ x: 1 } Point new"), "new Point {x: 1}.x");
+  }
+
+  #[test]
+  fn variant() {
+    assert_eq!(grouping("(1, a + 2)Rect::Shape"), "Shape::Rect(1, (a Add 2))");
+    assert_eq!(grouping("Empty::Shape"), "Shape::Empty()");
+  }
+
+  #[test]
+  fn function() {
+    assert_eq!(grouping("{ a * b }(a, b) fn"), "fn(b, a)");
+    assert_eq!(grouping("(1, { x }(x) fn)map"), "map(fn(x), 1)");
+    assert_eq!(grouping("{}() fn = f"), "(fn() Assign f)");
+    assert!(matches!(parse("{ x }(x) fn"), Ok(Expression::Function(function)) if function.name.is_none()));
+  }
+}
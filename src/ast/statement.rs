@@ -1,44 +1,89 @@
-use crate::token::{Grammar, Keyword, TokenStream};
+use crate::{
+  cst::SyntaxKind,
+  token::{Grammar, Keyword, TokenStream},
+};
 
-use self::{conditional::Conditional, function::Function};
+use self::{
+  conditional::Conditional, declaration::Declaration, enumeration::Enum, for_loop::ForLoop, function::Function,
+  matching::Match, record::Record, while_loop::WhileLoop,
+};
 
-use super::{expression::Expression, AstError, AstResult};
+use super::{expression::Expression, AstError, AstResult, Location};
 
 pub mod conditional;
+pub mod declaration;
+pub mod enumeration;
+pub mod for_loop;
 pub mod function;
+pub mod matching;
+pub mod record;
+pub mod while_loop;
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Statement<'a> {
   Conditional(Conditional<'a>),
+  Match(Match<'a>),
   Expression(Expression<'a>),
+  Declaration(Declaration<'a>),
   Function(Function<'a>),
+  Record(Record<'a>),
+  Enum(Enum<'a>),
   Return(Expression<'a>),
+  /// An expression run when the enclosing block finishes, however it finishes
+  Defer(Expression<'a>),
+  While(WhileLoop<'a>),
+  For(ForLoop<'a>),
+  Break(Location),
+  Continue(Location),
 }
 
 impl<'a> Statement<'a> {
   pub fn try_statement_opt(tokens: &mut TokenStream<'a>) -> AstResult<Option<Self>> {
-    tokens.skip_noop();
     if tokens.is_empty() {
       return Ok(None);
     }
 
+    let start = tokens.start_node();
+    let location = tokens.location();
     let statement = if tokens.try_keyword(Keyword::Return).is_ok() {
       Statement::Return(Expression::try_expression(tokens)?)
+    } else if tokens.try_keyword(Keyword::Defer).is_ok() {
+      Statement::Defer(Expression::try_expression(tokens)?)
+    } else if tokens.try_keyword(Keyword::Break).is_ok() {
+      Statement::Break(location)
+    } else if tokens.try_keyword(Keyword::Continue).is_ok() {
+      Statement::Continue(location)
+    } else if let Some(declaration) = Declaration::try_declaration_opt(tokens)? {
+      Statement::Declaration(declaration)
+    } else if let Some(while_loop) = WhileLoop::try_while_opt(tokens)? {
+      Statement::While(while_loop)
+    } else if let Some(for_loop) = ForLoop::try_for_opt(tokens)? {
+      Statement::For(for_loop)
     } else if let Some(conditional) = Conditional::try_conditional_opt(tokens)? {
       Statement::Conditional(conditional)
+    } else if let Some(matching) = Match::try_match_opt(tokens)? {
+      Statement::Match(matching)
     } else if let Some(function) = Function::try_function_opt(tokens)? {
       Statement::Function(function)
+    } else if let Some(record) = Record::try_record_opt(tokens)? {
+      Statement::Record(record)
+    } else if let Some(enumeration) = Enum::try_enum_opt(tokens)? {
+      Statement::Enum(enumeration)
     } else {
       Statement::Expression(Expression::try_expression(tokens)?)
     };
+    tokens.finish_node(SyntaxKind::Statement, start);
 
     Ok(Some(statement))
   }
 
   pub fn try_block(tokens: &mut TokenStream<'a>) -> AstResult<Vec<Self>> {
+    let start = tokens.start_node();
+    let location = tokens.location();
     tokens.try_grammar(Grammar::CloseCurly)?;
     let mut statements = Vec::new();
     loop {
+      tokens.check_unclosed(Grammar::CloseCurly, location)?;
       if tokens.try_grammar(Grammar::OpenCurly).is_ok() {
         break;
       }
@@ -48,6 +93,7 @@ impl<'a> Statement<'a> {
         return Err(AstError::MissingStatement(tokens.location()));
       }
     }
+    tokens.finish_node(SyntaxKind::Block, start);
 
     Ok(statements)
   }
@@ -7,111 +7,140 @@ use super::Statement;
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct Conditional<'a> {
-  // no else if for now to keep things simple
+  /// The `if` followed by any `else if` branches, in the order they're tested
+  pub branches: Vec<Branch<'a>>,
+  pub else_block: Vec<Statement<'a>>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Branch<'a> {
   pub condition: Expression<'a>,
-  pub true_block: Vec<Statement<'a>>,
-  pub false_block: Vec<Statement<'a>>,
+  pub block: Vec<Statement<'a>>,
   pub location: Location,
 }
 
 impl<'a> Conditional<'a> {
+  /// Reads `if a {} else if b {} else {}`, which is written `{} else {} b if else {} a if`
   pub fn try_conditional_opt(tokens: &mut TokenStream<'a>) -> AstResult<Option<Self>> {
     if tokens.try_keyword(Keyword::If).is_err() {
       return Ok(None);
     }
 
-    let location = tokens.location();
-    let condition = Expression::try_expression(tokens)?;
-    let true_block = Statement::try_block(tokens)?;
-    if tokens.try_keyword(Keyword::Else).is_ok() {
-      let false_block = Statement::try_block(tokens)?;
-      Ok(Some(Conditional {
+    let mut branches = Vec::new();
+    loop {
+      let location = tokens.location();
+      let condition = Expression::try_expression(tokens)?;
+      let block = Statement::try_block(tokens)?;
+      branches.push(Branch {
         condition,
-        true_block,
-        false_block,
+        block,
         location,
-      }))
-    } else {
-      Ok(Some(Conditional {
-        condition,
-        true_block,
-        false_block: Vec::new(),
-        location,
-      }))
+      });
+
+      if tokens.try_keyword(Keyword::Else).is_err() {
+        return Ok(Some(Conditional {
+          branches,
+          else_block: Vec::new(),
+        }));
+      }
+      if tokens.try_keyword(Keyword::If).is_err() {
+        break;
+      }
     }
+
+    let else_block = Statement::try_block(tokens)?;
+    Ok(Some(Conditional { branches, else_block }))
+  }
+
+  pub fn location(&self) -> Location {
+    self.branches[0].location
   }
 }
 
-// #[cfg(test)]
-// mod tests {
-//   use super::*;
-//   use crate::token::Operator;
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{ast::identifier::Identifier, token::Operator};
 
-//   #[test]
-//   fn conditional_else() {
-//     let mut tokens = TokenStream::new(
-//       "{
-//         2
-//       } else
-//       {
-//         1
-//       } true || false if",
-//     );
-//     assert_eq!(
-//       Conditional::try_conditional_opt(&mut tokens),
-//       Ok(Some(Conditional {
-//         condition: Expression::Operation {
-//           operator: Operator::Or,
-//           left: Box::new(Expression::Bool(true)),
-//           right: Box::new(Expression::Bool(false))
-//         },
-//         true_block: vec![Statement::Expression(Expression::Number(2.))],
-//         false_block: vec![Statement::Expression(Expression::Number(1.))]
-//       }))
-//     );
-//     assert_eq!(Conditional::try_conditional_opt(&mut tokens), Ok(None));
-//   }
+  fn number(number: f64, position: usize) -> Statement<'static> {
+    Statement::Expression(Expression::Number(number, Location::new(Some(position))))
+  }
+
+  #[test]
+  fn conditional_else() {
+    let mut tokens = TokenStream::new("{ 2 } else { 1 } true || false if").unwrap();
+    assert_eq!(
+      Conditional::try_conditional_opt(&mut tokens),
+      Ok(Some(Conditional {
+        branches: vec![Branch {
+          condition: Expression::Operation {
+            operator: Operator::Or,
+            left: Box::new(Expression::Bool(true, Location::new(Some(17)))),
+            right: Box::new(Expression::Bool(false, Location::new(Some(25)))),
+            location: Location::new(Some(22)),
+          },
+          block: vec![number(1., 13)],
+          location: Location::new(Some(25)),
+        }],
+        else_block: vec![number(2., 2)],
+      }))
+    );
+    assert_eq!(Conditional::try_conditional_opt(&mut tokens), Ok(None));
+  }
+
+  #[test]
+  fn conditional() {
+    let mut tokens = TokenStream::new("{ 1 } (true) if").unwrap();
+    assert_eq!(
+      Conditional::try_conditional_opt(&mut tokens),
+      Ok(Some(Conditional {
+        branches: vec![Branch {
+          condition: Expression::Brackets(
+            Box::new(Expression::Bool(true, Location::new(Some(7)))),
+            Location::new(Some(11))
+          ),
+          block: vec![number(1., 2)],
+          location: Location::new(Some(11)),
+        }],
+        else_block: vec![],
+      }))
+    );
+    assert_eq!(Conditional::try_conditional_opt(&mut tokens), Ok(None));
+  }
 
-//   #[test]
-//   fn conditional() {
-//     let mut tokens = TokenStream::new(
-//       "
-//       {
-//         1
-//       } true || false if",
-//     );
-//     assert_eq!(
-//       Conditional::try_conditional_opt(&mut tokens),
-//       Ok(Some(Conditional {
-//         condition: Expression::Operation {
-//           operator: Operator::Or,
-//           left: Box::new(Expression::Bool(true)),
-//           right: Box::new(Expression::Bool(false))
-//         },
-//         true_block: vec![],
-//         false_block: vec![Statement::Expression(Expression::Number(1.))]
-//       }))
-//     );
-//     assert_eq!(Conditional::try_conditional_opt(&mut tokens), Ok(None));
-//   }
+  #[test]
+  fn else_if() {
+    let mut tokens = TokenStream::new("{ 3 } else { 2 } b if else { 1 } a if").unwrap();
+    let conditional = Conditional::try_conditional_opt(&mut tokens).unwrap().unwrap();
+    let conditions: Vec<_> = conditional
+      .branches
+      .iter()
+      .map(|branch| (&branch.condition, branch.location))
+      .collect();
+    assert_eq!(
+      conditions,
+      [
+        (
+          &Expression::Identifier(Identifier("a"), Location::new(Some(33))),
+          Location::new(Some(33))
+        ),
+        (
+          &Expression::Identifier(Identifier("b"), Location::new(Some(17))),
+          Location::new(Some(17))
+        ),
+      ]
+    );
+    assert_eq!(conditional.branches[0].block, vec![number(1., 29)]);
+    assert_eq!(conditional.branches[1].block, vec![number(2., 13)]);
+    assert_eq!(conditional.else_block, vec![number(3., 2)]);
+    assert!(tokens.is_empty());
+  }
 
-//   #[test]
-//   fn conditional_brackets() {
-//     let mut tokens = TokenStream::new(
-//       "
-//       {
-//         1
-//       }
-//       (true) if",
-//     );
-//     assert_eq!(
-//       Conditional::try_conditional_opt(&mut tokens),
-//       Ok(Some(Conditional {
-//         condition: Expression::Brackets(Box::new(Expression::Bool(true))),
-//         true_block: vec![],
-//         false_block: vec![Statement::Expression(Expression::Number(1.))]
-//       }))
-//     );
-//     assert_eq!(Conditional::try_conditional_opt(&mut tokens), Ok(None));
-//   }
-// }
+  #[test]
+  fn else_if_without_else() {
+    let mut tokens = TokenStream::new("{ 2 } b if else { 1 } a if").unwrap();
+    let conditional = Conditional::try_conditional_opt(&mut tokens).unwrap().unwrap();
+    assert_eq!(conditional.branches.len(), 2);
+    assert!(conditional.else_block.is_empty());
+  }
+}
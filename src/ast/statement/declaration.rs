@@ -0,0 +1,90 @@
+use crate::{
+  ast::{expression::Expression, identifier::Identifier, AstResult, Location},
+  token::{Keyword, Operator, TokenStream},
+};
+
+/// A variable local to the enclosing block, shadowing any of the same name outside it
+#[derive(Debug, PartialEq, Clone)]
+pub struct Declaration<'a> {
+  pub name: Identifier<'a>,
+  /// `None` when there's no value; a constant always has one
+  pub value: Option<Expression<'a>>,
+  /// Declared with `const` rather than `let`, so it can't be reassigned
+  pub constant: bool,
+  pub location: Location,
+}
+
+impl<'a> Declaration<'a> {
+  /// Reads `let x = 1`, which is written `1 = x let`, `let x`, written `x let`, or `const x = 1`, written
+  /// `1 = x const`
+  pub fn try_declaration_opt(tokens: &mut TokenStream<'a>) -> AstResult<Option<Self>> {
+    let constant = if tokens.try_keyword(Keyword::Const).is_ok() {
+      true
+    } else if tokens.try_keyword(Keyword::Let).is_ok() {
+      false
+    } else {
+      return Ok(None);
+    };
+
+    let location = tokens.location();
+    let name = tokens.try_identifier()?;
+    let value = if constant {
+      tokens.try_operator(Operator::Assign)?;
+      Some(Expression::try_expression(tokens)?)
+    } else if tokens.try_operator(Operator::Assign).is_ok() {
+      Some(Expression::try_expression(tokens)?)
+    } else {
+      None
+    };
+    Ok(Some(Declaration {
+      name,
+      value,
+      constant,
+      location,
+    }))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn declaration() {
+    let mut tokens = TokenStream::new("x let\n1 = x let").unwrap();
+    assert_eq!(
+      Declaration::try_declaration_opt(&mut tokens),
+      Ok(Some(Declaration {
+        name: Identifier("x"),
+        value: Some(Expression::Number(1., Location::new(Some(6)))),
+        constant: false,
+        location: Location::new(Some(10)),
+      }))
+    );
+    assert_eq!(
+      Declaration::try_declaration_opt(&mut tokens),
+      Ok(Some(Declaration {
+        name: Identifier("x"),
+        value: None,
+        constant: false,
+        location: Location::new(Some(0)),
+      }))
+    );
+    assert_eq!(Declaration::try_declaration_opt(&mut tokens), Ok(None));
+  }
+
+  #[test]
+  fn constant() {
+    let mut tokens = TokenStream::new("1 = x const").unwrap();
+    assert!(matches!(
+      Declaration::try_declaration_opt(&mut tokens),
+      Ok(Some(Declaration {
+        value: Some(_),
+        constant: true,
+        ..
+      }))
+    ));
+    let mut tokens = TokenStream::new("x const").unwrap();
+    assert!(Declaration::try_declaration_opt(&mut tokens).is_err());
+  }
+}
@@ -0,0 +1,118 @@
+use crate::{
+  ast::{identifier::Identifier, AstResult, Location},
+  token::{Grammar, Keyword, TokenStream},
+};
+use derive_more::Display;
+
+#[derive(Debug, PartialEq, Clone, Display)]
+#[display(fmt = "Enum({})", name)]
+pub struct Enum<'a> {
+  pub name: Identifier<'a>,
+  /// Variants in the order they're written
+  pub variants: Vec<Variant<'a>>,
+  pub location: Location,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Variant<'a> {
+  pub name: Identifier<'a>,
+  /// Names of the payload's fields in the order they're written, empty if it has no payload
+  pub fields: Vec<Identifier<'a>>,
+}
+
+impl<'a> Enum<'a> {
+  /// Reads `enum Shape { Circle(r), Empty }`, which is written `{ Empty, (r)Circle } Shape enum`
+  pub fn try_enum_opt(tokens: &mut TokenStream<'a>) -> AstResult<Option<Self>> {
+    if tokens.try_keyword(Keyword::Enum).is_err() {
+      return Ok(None);
+    }
+
+    let location = tokens.location();
+    let name = tokens.try_identifier()?;
+    let curly_location = tokens.location();
+    tokens.try_grammar(Grammar::CloseCurly)?;
+
+    let mut variants = Vec::new();
+    loop {
+      tokens.check_unclosed(Grammar::CloseCurly, curly_location)?;
+      if tokens.try_grammar(Grammar::OpenCurly).is_ok() {
+        break;
+      }
+      let name = tokens.try_identifier()?;
+      let fields = try_fields_opt(tokens)?;
+      variants.push(Variant { name, fields });
+
+      if tokens.try_grammar(Grammar::Comma).is_err() {
+        tokens.try_closing_grammar(Grammar::CloseCurly, curly_location)?;
+        break;
+      }
+    }
+    variants.reverse();
+
+    Ok(Some(Enum {
+      name,
+      variants,
+      location,
+    }))
+  }
+
+  pub fn variant(&self, name: &Identifier) -> Option<&Variant<'a>> {
+    self.variants.iter().find(|variant| variant.name == *name)
+  }
+}
+
+/// Bracketed names such as `(a, b)`, in the order they're written. Empty if there are no brackets.
+pub(super) fn try_fields_opt<'a>(tokens: &mut TokenStream<'a>) -> AstResult<Vec<Identifier<'a>>> {
+  let mut fields = Vec::new();
+  let bracket_location = tokens.location();
+  if tokens.try_grammar(Grammar::CloseBracket).is_err() {
+    return Ok(fields);
+  }
+
+  loop {
+    tokens.check_unclosed(Grammar::CloseBracket, bracket_location)?;
+    if tokens.try_grammar(Grammar::OpenBracket).is_ok() {
+      break;
+    }
+    fields.push(tokens.try_identifier()?);
+
+    if tokens.try_grammar(Grammar::Comma).is_err() {
+      tokens.try_closing_grammar(Grammar::CloseBracket, bracket_location)?;
+      break;
+    }
+  }
+  fields.reverse();
+  Ok(fields)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn enumeration() {
+    let mut tokens = TokenStream::new("{ Empty, (w, h)Rect, (r)Circle } Shape enum").unwrap();
+    assert_eq!(
+      Enum::try_enum_opt(&mut tokens),
+      Ok(Some(Enum {
+        name: Identifier("Shape"),
+        variants: vec![
+          Variant {
+            name: Identifier("Empty"),
+            fields: vec![],
+          },
+          Variant {
+            name: Identifier("Rect"),
+            fields: vec![Identifier("w"), Identifier("h")],
+          },
+          Variant {
+            name: Identifier("Circle"),
+            fields: vec![Identifier("r")],
+          },
+        ],
+        location: Location::new(Some(33)),
+      }))
+    );
+    assert_eq!(Enum::try_enum_opt(&mut tokens), Ok(None));
+  }
+}
@@ -0,0 +1,128 @@
+use crate::{
+  ast::{expression::Expression, identifier::Identifier, AstResult, Location},
+  token::{Grammar, Keyword, TokenStream},
+};
+
+use super::Statement;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct ForLoop<'a> {
+  pub variable: Identifier<'a>,
+  pub iterable: Iterable<'a>,
+  pub block: Vec<Statement<'a>>,
+  pub location: Location,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Iterable<'a> {
+  /// Numbers from `start` up to but excluding `end`, counting by `step` which defaults to 1
+  Range {
+    start: Expression<'a>,
+    end: Expression<'a>,
+    step: Option<Expression<'a>>,
+  },
+  /// Each element of a list, key of a map, or character of a string
+  Expression(Expression<'a>),
+}
+
+impl<'a> ForLoop<'a> {
+  /// Reads `for i in 0..10 step 2 {}`, which is written `{} 2 step 10..0 in i for`
+  pub fn try_for_opt(tokens: &mut TokenStream<'a>) -> AstResult<Option<Self>> {
+    if tokens.try_keyword(Keyword::For).is_err() {
+      return Ok(None);
+    }
+
+    let location = tokens.location();
+    let variable = tokens.try_identifier()?;
+    tokens.try_keyword(Keyword::In)?;
+    let expression = Expression::try_expression(tokens)?;
+    let iterable = if tokens.try_grammar(Grammar::Range).is_ok() {
+      let end = Expression::try_expression(tokens)?;
+      let step = if tokens.try_keyword(Keyword::Step).is_ok() {
+        Some(Expression::try_expression(tokens)?)
+      } else {
+        None
+      };
+      Iterable::Range {
+        start: expression,
+        end,
+        step,
+      }
+    } else {
+      Iterable::Expression(expression)
+    };
+    let block = Statement::try_block(tokens)?;
+
+    Ok(Some(ForLoop {
+      variable,
+      iterable,
+      block,
+      location,
+    }))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::ast::AstError;
+
+  fn number(number: f64, position: usize) -> Expression<'static> {
+    Expression::Number(number, Location::new(Some(position)))
+  }
+
+  #[test]
+  fn range() {
+    let mut tokens = TokenStream::new("{ i } 10..0 in i for").unwrap();
+    assert_eq!(
+      ForLoop::try_for_opt(&mut tokens),
+      Ok(Some(ForLoop {
+        variable: Identifier("i"),
+        iterable: Iterable::Range {
+          start: number(0., 10),
+          end: number(10., 6),
+          step: None,
+        },
+        block: vec![Statement::Expression(Expression::Identifier(
+          Identifier("i"),
+          Location::new(Some(2))
+        ))],
+        location: Location::new(Some(15)),
+      }))
+    );
+    assert_eq!(ForLoop::try_for_opt(&mut tokens), Ok(None));
+  }
+
+  #[test]
+  fn range_step() {
+    let mut tokens = TokenStream::new("{} -1 step 0..n in i for").unwrap();
+    let for_loop = ForLoop::try_for_opt(&mut tokens).unwrap().unwrap();
+    assert_eq!(
+      for_loop.iterable,
+      Iterable::Range {
+        start: Expression::Identifier(Identifier("n"), Location::new(Some(14))),
+        end: number(0., 11),
+        step: Some(number(-1., 3)),
+      }
+    );
+  }
+
+  #[test]
+  fn string() {
+    let mut tokens = TokenStream::new("{} \"abc\" in c for").unwrap();
+    let for_loop = ForLoop::try_for_opt(&mut tokens).unwrap().unwrap();
+    assert_eq!(
+      for_loop.iterable,
+      Iterable::Expression(Expression::String("abc".to_string(), Location::new(Some(3))))
+    );
+  }
+
+  #[test]
+  fn missing_in() {
+    let mut tokens = TokenStream::new("{} 10..0 i for").unwrap();
+    assert!(matches!(
+      ForLoop::try_for_opt(&mut tokens),
+      Err(AstError::TokenError(_))
+    ));
+  }
+}
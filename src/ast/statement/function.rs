@@ -1,32 +1,67 @@
+use std::fmt;
+
 use crate::{
   ast::{identifier::Identifier, AstResult, Location},
-  token::{Grammar, Keyword, TokenStream},
+  token::{Grammar, Keyword, TokenKind, TokenStream},
 };
-use derive_more::Display;
 
 use super::Statement;
 
-#[derive(Debug, PartialEq, Clone, Display)]
-#[display(fmt = "Function({})", name)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct Function<'a> {
-  pub name: Identifier<'a>,
+  /// `None` for a function written as an expression, e.g. `{ x * 2 }(x) fn`
+  pub name: Option<Identifier<'a>>,
   pub arguments: Vec<Identifier<'a>>,
   pub block: Vec<Statement<'a>>,
+  /// Doc comments (`\\\`) read directly before the `fn`, i.e. placed below it
+  pub docs: Option<String>,
+  /// Declared with `mut fn`, so its name can be reassigned. Other declared functions are constants.
+  pub mutable: bool,
   pub location: Location,
 }
 
 impl<'a> Function<'a> {
+  /// A named function declaration, `fn name(...) { ... }` or `mut fn name(...) { ... }`
   pub fn try_function_opt(tokens: &mut TokenStream<'a>) -> AstResult<Option<Self>> {
-    if tokens.try_keyword(Keyword::Fn).is_err() {
+    let docs = tokens.peek_docs();
+    let mutable = tokens.try_keyword(Keyword::Mut).is_ok();
+    let named = matches!(tokens.peek(), Some(token) if token.kind == TokenKind::Keyword(Keyword::Fn))
+      && matches!(tokens.peek_nth(1), Some(token) if matches!(token.kind, TokenKind::Identifier(_)));
+    if !named && !mutable {
       return Ok(None);
     }
 
+    tokens.try_keyword(Keyword::Fn)?;
     let location = tokens.location();
     let name = tokens.try_identifier()?;
+    let mut function = Function::try_rest(tokens, Some(name), docs, location)?;
+    function.mutable = mutable;
+    Ok(Some(function))
+  }
+
+  /// An anonymous function, `fn(...) { ... }`, which is a value rather than a declaration
+  pub fn try_anonymous_opt(tokens: &mut TokenStream<'a>) -> AstResult<Option<Self>> {
+    let location = tokens.location();
+    if tokens.try_keyword(Keyword::Fn).is_err() {
+      return Ok(None);
+    }
+
+    Function::try_rest(tokens, None, None, location).map(Some)
+  }
+
+  /// The arguments and block, after the `fn` and name have been consumed
+  fn try_rest(
+    tokens: &mut TokenStream<'a>,
+    name: Option<Identifier<'a>>,
+    docs: Option<String>,
+    location: Location,
+  ) -> AstResult<Self> {
+    let bracket_location = tokens.location();
     tokens.try_grammar(Grammar::CloseBracket)?;
 
     let mut arguments = Vec::new();
     loop {
+      tokens.check_unclosed(Grammar::CloseBracket, bracket_location)?;
       if tokens.try_grammar(Grammar::OpenBracket).is_ok() {
         // end of arguments
         break;
@@ -35,19 +70,30 @@ impl<'a> Function<'a> {
 
       if tokens.try_grammar(Grammar::Comma).is_err() {
         // no comma, this must also be the end of arguments, expect an open bracket
-        tokens.try_grammar(Grammar::OpenBracket)?;
+        tokens.try_closing_grammar(Grammar::CloseBracket, bracket_location)?;
         break;
       }
     }
 
     let block = Statement::try_block(tokens)?;
 
-    Ok(Some(Function {
+    Ok(Function {
       name,
       arguments,
       block,
+      docs,
+      mutable: false,
       location,
-    }))
+    })
+  }
+}
+
+impl fmt::Display for Function<'_> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self.name {
+      Some(name) => write!(f, "Function({name})"),
+      None => f.write_str("Function(anonymous)"),
+    }
   }
 }
 
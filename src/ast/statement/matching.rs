@@ -0,0 +1,126 @@
+use crate::{
+  ast::{expression::Expression, identifier::Identifier, AstResult, Location},
+  token::{Grammar, Keyword, TokenStream},
+};
+
+use super::{enumeration::try_fields_opt, Statement};
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Match<'a> {
+  pub value: Expression<'a>,
+  /// Arms in the order they're tested, i.e. reading order
+  pub arms: Vec<Arm<'a>>,
+  pub location: Location,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Arm<'a> {
+  pub pattern: Pattern<'a>,
+  pub block: Vec<Statement<'a>>,
+  pub location: Location,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Pattern<'a> {
+  /// `Shape::Circle(r)`, which is written `(r)Circle::Shape`, binding the payload's fields in order
+  Variant {
+    enumeration: Identifier<'a>,
+    variant: Identifier<'a>,
+    bindings: Vec<Identifier<'a>>,
+  },
+  /// `_`, which matches anything
+  Wildcard,
+}
+
+impl<'a> Match<'a> {
+  /// Reads `match shape { Shape::Circle(r) {} _ {} }`, which is written `{ {} _ {} (r)Circle::Shape } shape match`
+  pub fn try_match_opt(tokens: &mut TokenStream<'a>) -> AstResult<Option<Self>> {
+    if tokens.try_keyword(Keyword::Match).is_err() {
+      return Ok(None);
+    }
+
+    let location = tokens.location();
+    let value = Expression::try_expression(tokens)?;
+    let curly_location = tokens.location();
+    tokens.try_grammar(Grammar::CloseCurly)?;
+
+    let mut arms = Vec::new();
+    loop {
+      tokens.check_unclosed(Grammar::CloseCurly, curly_location)?;
+      if tokens.try_grammar(Grammar::OpenCurly).is_ok() {
+        break;
+      }
+      let location = tokens.location();
+      let pattern = Pattern::try_pattern(tokens)?;
+      let block = Statement::try_block(tokens)?;
+      arms.push(Arm {
+        pattern,
+        block,
+        location,
+      });
+    }
+
+    Ok(Some(Match { value, arms, location }))
+  }
+}
+
+impl<'a> Pattern<'a> {
+  fn try_pattern(tokens: &mut TokenStream<'a>) -> AstResult<Self> {
+    let enumeration = tokens.try_identifier()?;
+    if enumeration == Identifier("_") {
+      return Ok(Pattern::Wildcard);
+    }
+
+    tokens.try_grammar(Grammar::PathSeparator)?;
+    let variant = tokens.try_identifier()?;
+    let bindings = try_fields_opt(tokens)?;
+    Ok(Pattern::Variant {
+      enumeration,
+      variant,
+      bindings,
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn match_arms() {
+    let mut tokens = TokenStream::new("{ { 2 } _ { r } (r)Circle::Shape } shape match").unwrap();
+    let matching = Match::try_match_opt(&mut tokens).unwrap().unwrap();
+    assert_eq!(
+      matching.value,
+      Expression::Identifier(Identifier("shape"), Location::new(Some(35)))
+    );
+    let patterns: Vec<_> = matching.arms.iter().map(|arm| &arm.pattern).collect();
+    assert_eq!(
+      patterns,
+      [
+        &Pattern::Variant {
+          enumeration: Identifier("Shape"),
+          variant: Identifier("Circle"),
+          bindings: vec![Identifier("r")],
+        },
+        &Pattern::Wildcard,
+      ]
+    );
+    assert_eq!(matching.arms[0].location, Location::new(Some(27)));
+    assert!(tokens.is_empty());
+  }
+
+  #[test]
+  fn unit_variant() {
+    let mut tokens = TokenStream::new("{ {} Empty::Shape } shape match").unwrap();
+    let matching = Match::try_match_opt(&mut tokens).unwrap().unwrap();
+    assert_eq!(
+      matching.arms[0].pattern,
+      Pattern::Variant {
+        enumeration: Identifier("Shape"),
+        variant: Identifier("Empty"),
+        bindings: vec![],
+      }
+    );
+  }
+}
@@ -0,0 +1,71 @@
+use crate::{
+  ast::{identifier::Identifier, AstResult, Location},
+  token::{Grammar, Keyword, TokenStream},
+};
+use derive_more::Display;
+
+#[derive(Debug, PartialEq, Clone, Display)]
+#[display(fmt = "Record({})", name)]
+pub struct Record<'a> {
+  pub name: Identifier<'a>,
+  /// Field names in the order they're written
+  pub fields: Vec<Identifier<'a>>,
+  pub location: Location,
+}
+
+impl<'a> Record<'a> {
+  /// Reads `record Point { x, y }`, which is written `{ x, y } Point record`
+  pub fn try_record_opt(tokens: &mut TokenStream<'a>) -> AstResult<Option<Self>> {
+    if tokens.try_keyword(Keyword::Record).is_err() {
+      return Ok(None);
+    }
+
+    let location = tokens.location();
+    let name = tokens.try_identifier()?;
+    let curly_location = tokens.location();
+    tokens.try_grammar(Grammar::CloseCurly)?;
+
+    let mut fields = Vec::new();
+    loop {
+      tokens.check_unclosed(Grammar::CloseCurly, curly_location)?;
+      if tokens.try_grammar(Grammar::OpenCurly).is_ok() {
+        break;
+      }
+      fields.push(tokens.try_identifier()?);
+
+      if tokens.try_grammar(Grammar::Comma).is_err() {
+        tokens.try_closing_grammar(Grammar::CloseCurly, curly_location)?;
+        break;
+      }
+    }
+    fields.reverse();
+
+    Ok(Some(Record { name, fields, location }))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn record() {
+    let mut tokens = TokenStream::new("{ x, y } Point record").unwrap();
+    assert_eq!(
+      Record::try_record_opt(&mut tokens),
+      Ok(Some(Record {
+        name: Identifier("Point"),
+        fields: vec![Identifier("x"), Identifier("y")],
+        location: Location::new(Some(9)),
+      }))
+    );
+    assert_eq!(Record::try_record_opt(&mut tokens), Ok(None));
+  }
+
+  #[test]
+  fn empty_record() {
+    let mut tokens = TokenStream::new("{} Unit record").unwrap();
+    let record = Record::try_record_opt(&mut tokens).unwrap().unwrap();
+    assert!(record.fields.is_empty());
+  }
+}
@@ -0,0 +1,56 @@
+use crate::{
+  ast::{expression::Expression, AstResult, Location},
+  token::{Keyword, TokenStream},
+};
+
+use super::Statement;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct WhileLoop<'a> {
+  pub condition: Expression<'a>,
+  pub block: Vec<Statement<'a>>,
+  pub location: Location,
+}
+
+impl<'a> WhileLoop<'a> {
+  /// Reads `while a {}`, which is written `{} a while`
+  pub fn try_while_opt(tokens: &mut TokenStream<'a>) -> AstResult<Option<Self>> {
+    if tokens.try_keyword(Keyword::While).is_err() {
+      return Ok(None);
+    }
+
+    let location = tokens.location();
+    let condition = Expression::try_expression(tokens)?;
+    let block = Statement::try_block(tokens)?;
+    Ok(Some(WhileLoop {
+      condition,
+      block,
+      location,
+    }))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{ast::identifier::Identifier, token::Operator};
+
+  #[test]
+  fn while_loop() {
+    let mut tokens = TokenStream::new("{ break } i < 3 while").unwrap();
+    assert_eq!(
+      WhileLoop::try_while_opt(&mut tokens),
+      Ok(Some(WhileLoop {
+        condition: Expression::Operation {
+          operator: Operator::Lt,
+          left: Box::new(Expression::Identifier(Identifier("i"), Location::new(Some(10)))),
+          right: Box::new(Expression::Number(3., Location::new(Some(14)))),
+          location: Location::new(Some(12)),
+        },
+        block: vec![Statement::Break(Location::new(Some(2)))],
+        location: Location::new(Some(14)),
+      }))
+    );
+    assert_eq!(WhileLoop::try_while_opt(&mut tokens), Ok(None));
+  }
+}
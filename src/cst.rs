@@ -0,0 +1,156 @@
+//! A lossless concrete syntax tree, which keeps all whitespace and comments so the original source can be reproduced
+//! byte-for-byte. This is intended for tooling such as formatters, rather than the interpreter.
+
+use std::{cmp::Reverse, fmt, iter::Peekable};
+
+use crate::{
+  ast::{Ast, AstError, AstResult},
+  token::{NodeRange, Token, TokenStream, Trivia},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyntaxKind {
+  Root,
+  Statement,
+  Block,
+  Expression,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SyntaxElement<'a> {
+  Node(SyntaxNode<'a>),
+  Token(Token<'a>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxNode<'a> {
+  pub kind: SyntaxKind,
+  /// Children in reading order, i.e. the reverse of the order they appear in the file
+  pub children: Vec<SyntaxElement<'a>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxTree<'a> {
+  pub root: SyntaxNode<'a>,
+  /// Trivia before the first token in the file, which is read last
+  pub trailing_trivia: Vec<Trivia<'a>>,
+}
+
+impl<'a> SyntaxTree<'a> {
+  /// Parse the source, returning both the abstract and the lossless concrete syntax tree
+  pub fn parse(string: &'a str) -> AstResult<(Ast<'a>, Self)> {
+    let mut tokens = TokenStream::new_lossless(string).map_err(AstError::from)?;
+    let ast = Ast::new(&mut tokens)?;
+    let (tokens, trailing_trivia, nodes) = tokens.into_parts();
+
+    // sort outer nodes before the nodes they contain. Nodes are recorded once finished, so when two nodes cover the
+    // same tokens the later one is the outer.
+    let mut nodes: Vec<(usize, NodeRange)> = nodes.into_iter().enumerate().collect();
+    nodes.sort_by_key(|(finished, node)| (node.start, Reverse(node.end), Reverse(*finished)));
+
+    let mut tokens = tokens.into_iter().enumerate().peekable();
+    let mut nodes = nodes.into_iter().map(|(_, node)| node).peekable();
+    let root = SyntaxNode::build(SyntaxKind::Root, usize::MAX, &mut tokens, &mut nodes);
+    Ok((ast, SyntaxTree { root, trailing_trivia }))
+  }
+}
+
+impl<'a> SyntaxNode<'a> {
+  fn build(
+    kind: SyntaxKind,
+    end: usize,
+    tokens: &mut Peekable<impl Iterator<Item = (usize, Token<'a>)>>,
+    nodes: &mut Peekable<impl Iterator<Item = NodeRange>>,
+  ) -> Self {
+    let mut children = Vec::new();
+    while let Some((index, _)) = tokens.peek() {
+      let index = *index;
+      if index >= end {
+        break;
+      }
+
+      if let Some(node) = nodes.next_if(|node| node.start == index) {
+        children.push(SyntaxElement::Node(SyntaxNode::build(
+          node.kind, node.end, tokens, nodes,
+        )));
+      } else {
+        let (_, token) = tokens.next().unwrap();
+        children.push(SyntaxElement::Token(token));
+      }
+    }
+
+    SyntaxNode { kind, children }
+  }
+
+  /// All tokens within the node, in reading order
+  pub fn tokens(&self) -> Box<dyn Iterator<Item = &Token<'a>> + '_> {
+    Box::new(self.children.iter().flat_map(|child| match child {
+      SyntaxElement::Node(node) => node.tokens(),
+      SyntaxElement::Token(token) => Box::new(std::iter::once(token)),
+    }))
+  }
+}
+
+impl fmt::Display for SyntaxTree<'_> {
+  /// Writes the original source
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    for trivia in &self.trailing_trivia {
+      f.write_str(trivia.text)?;
+    }
+    let tokens: Vec<_> = self.root.tokens().collect();
+    for token in tokens.into_iter().rev() {
+      f.write_str(token.text)?;
+      for trivia in &token.trivia {
+        f.write_str(trivia.text)?;
+      }
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::token::TokenKind;
+
+  #[test]
+  fn round_trip() {
+    for source in [
+      include_str!("../examples/example.stop"),
+      include_str!("../examples/fib.stop"),
+      include_str!("../examples/fizzbuzz.stop"),
+      include_str!("../examples/hello.stop"),
+      "",
+      "  \\* only trivia *\\ \n",
+    ] {
+      let (_, tree) = SyntaxTree::parse(source).unwrap();
+      assert_eq!(tree.to_string(), source);
+    }
+  }
+
+  #[test]
+  fn structure() {
+    let (_, tree) = SyntaxTree::parse("{\n  1 + 2\n} true if\na comment \\\\").unwrap();
+    let statement = match &tree.root.children[..] {
+      [SyntaxElement::Node(statement)] => statement,
+      children => panic!("expected a single statement, found {children:?}"),
+    };
+    assert_eq!(statement.kind, SyntaxKind::Statement);
+
+    let kinds: Vec<_> = statement
+      .children
+      .iter()
+      .map(|child| match child {
+        SyntaxElement::Node(node) => Err(node.kind),
+        SyntaxElement::Token(token) => Ok(token.kind.clone()),
+      })
+      .collect();
+    assert_eq!(kinds[1], Err(SyntaxKind::Expression));
+    assert_eq!(kinds[2], Err(SyntaxKind::Block));
+    match &statement.children[0] {
+      SyntaxElement::Token(token) => assert_eq!(token.trivia[1].text, "a comment \\\\"),
+      _ => panic!("expected a token"),
+    }
+    assert!(matches!(kinds[0], Ok(TokenKind::Keyword(_))));
+  }
+}
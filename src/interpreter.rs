@@ -1,13 +1,15 @@
 use crate::ast::{statement::Statement, Ast, Location};
 use thiserror::Error;
 
-use self::{scope::ScopeStack, variable::Variable};
+use self::scope::ScopeStack;
 
 mod expression;
 mod operator;
 mod scope;
 mod statement;
 mod stopstd;
+#[cfg(test)]
+pub(crate) mod test_util;
 mod variable;
 
 #[derive(Error, Debug, PartialEq, Eq)]
@@ -18,6 +20,11 @@ pub enum RuntimeError {
   InvalidType { expected: &'static str, location: Location },
   #[error("invalid expression, expected {expected}")]
   InvalidExpression { expected: &'static str, location: Location },
+  #[error("a value of type {type_name} is not callable")]
+  NotCallable {
+    type_name: &'static str,
+    location: Location,
+  },
   #[error("invalid number of arguments in call to '{function_name}', received: {received}, expected: {expected}")]
   IncorrectArgumentCount {
     function_name: String,
@@ -25,6 +32,38 @@ pub enum RuntimeError {
     received: usize,
     location: Location,
   },
+  #[error("index {index} is out of range for a list of length {length}")]
+  IndexOutOfRange {
+    index: i64,
+    length: usize,
+    location: Location,
+  },
+  #[error("key '{key}' is not in the map")]
+  MissingKey { key: String, location: Location },
+  #[error("record '{record}' has no field '{field}'")]
+  UnknownField {
+    record: String,
+    field: String,
+    location: Location,
+  },
+  #[error("missing field '{field}' of record '{record}'")]
+  MissingField {
+    record: String,
+    field: String,
+    location: Location,
+  },
+  #[error("enum '{enumeration}' has no variant '{variant}'")]
+  UnknownVariant {
+    enumeration: String,
+    variant: String,
+    location: Location,
+  },
+  #[error("no match arm for '{value}'")]
+  NoMatchingArm { value: String, location: Location },
+  #[error("'{name}' is a constant and can't be reassigned")]
+  AssignToConstant { name: String, location: Location },
+  #[error("a loop's step can't be zero")]
+  ZeroStep { location: Location },
 }
 pub type RuntimeResult<T> = Result<T, RuntimeError>;
 impl RuntimeError {
@@ -33,7 +72,16 @@ impl RuntimeError {
       RuntimeError::UnknownVariable { location, .. }
       | RuntimeError::InvalidType { location, .. }
       | RuntimeError::InvalidExpression { location, .. }
-      | RuntimeError::IncorrectArgumentCount { location, .. } => *location,
+      | RuntimeError::NotCallable { location, .. }
+      | RuntimeError::IncorrectArgumentCount { location, .. }
+      | RuntimeError::IndexOutOfRange { location, .. }
+      | RuntimeError::MissingKey { location, .. }
+      | RuntimeError::UnknownField { location, .. }
+      | RuntimeError::MissingField { location, .. }
+      | RuntimeError::UnknownVariant { location, .. }
+      | RuntimeError::NoMatchingArm { location, .. }
+      | RuntimeError::AssignToConstant { location, .. }
+      | RuntimeError::ZeroStep { location } => *location,
     }
   }
 }
@@ -43,7 +91,3 @@ pub fn interpret(ast: Ast<'_>) -> RuntimeResult<()> {
   Statement::eval_block(&mut scope, &ast.statements)?;
   Ok(())
 }
-
-trait Eval<'a> {
-  fn eval(&self, scope: &mut ScopeStack<'a>, location: Location) -> RuntimeResult<Variable<'a>>;
-}
@@ -1,55 +1,603 @@
-use crate::ast::{expression::Expression, statement::Statement, Location};
+use std::collections::BTreeMap;
+
+use crate::ast::{expression::Expression, statement::Statement};
 
 use super::{
-  scope::ScopeStack, statement::StatementValue, stopstd::std_call, variable::Variable, Eval, RuntimeError,
-  RuntimeResult,
+  scope::ScopeStack,
+  statement::StatementValue,
+  stopstd::std_call,
+  variable::{unknown_field, Closure, EnumValue, RecordValue, Variable},
+  RuntimeError, RuntimeResult,
 };
 
+/// Changes a variable in place, boxed as [`Expression::place`] wraps it once per level of nesting
+type Update<'s, 'a> = Box<dyn FnOnce(&mut Variable<'a>) -> RuntimeResult<()> + 's>;
+
+/// Chooses part of a variable, boxed as [`Expression::get_within`] wraps it once per level of nesting
+type Select<'s, 'a> = Box<dyn for<'v> FnOnce(&'v Variable<'a>) -> RuntimeResult<&'v Variable<'a>> + 's>;
+
 impl<'a> Expression<'a> {
   pub fn eval(&self, scope: &mut ScopeStack<'a>) -> RuntimeResult<Variable<'a>> {
     match self {
       Expression::Bool(bool, _) => Ok(Variable::Bool(*bool)),
-      Expression::String(str, _) => Ok(Variable::String(str.to_string())),
+      Expression::String(string, _) => Ok(Variable::String(string.clone())),
       Expression::Number(num, _) => Ok(Variable::Number(*num)),
-      Expression::Identifier(name, location) => Ok(scope.get(name, *location)?.clone()), // variables are always copied
-      Expression::Brackets(expr, location) => expr.eval(scope),
+      Expression::Identifier(name, location) => scope.get(name, *location),
+      Expression::Brackets(expr, _) => expr.eval(scope),
+      Expression::List(elements, _) => Ok(Variable::List(
+        elements
+          .iter()
+          .map(|element| element.eval(scope))
+          .collect::<RuntimeResult<_>>()?,
+      )),
+      Expression::Map(entries, _) => {
+        let mut map = BTreeMap::new();
+        for (key, value) in entries {
+          let key_location = key.location();
+          let key = key.eval(scope)?.try_into_str(key_location)?.to_string();
+          map.insert(key, value.eval(scope)?);
+        }
+        Ok(Variable::Map(map))
+      }
+      Expression::Construct {
+        record,
+        fields,
+        location,
+      } => {
+        let record = scope.get(record, *location)?.try_into_record_type(*location)?.clone();
+        let mut values = Vec::with_capacity(fields.len());
+        for (field, value) in fields {
+          if !record.fields.contains(field) {
+            return Err(unknown_field(record.name, field, value.location()));
+          }
+          values.push((*field, value.eval(scope)?));
+        }
+
+        let fields = record
+          .fields
+          .iter()
+          .map(|field| match values.iter().position(|(name, _)| name == field) {
+            Some(i) => Ok((*field, values.swap_remove(i).1)),
+            None => Err(RuntimeError::MissingField {
+              record: record.name.to_string(),
+              field: field.to_string(),
+              location: *location,
+            }),
+          })
+          .collect::<RuntimeResult<_>>()?;
+        Ok(Variable::Record(RecordValue {
+          record: record.name,
+          fields,
+        }))
+      }
+      Expression::Variant {
+        enumeration,
+        variant,
+        arguments,
+        location,
+      } => {
+        let declared = scope.get(enumeration, *location)?;
+        let declared = declared.try_into_enum_type(*location)?;
+        let Some(declared_variant) = declared.variant(variant) else {
+          return Err(RuntimeError::UnknownVariant {
+            enumeration: enumeration.to_string(),
+            variant: variant.to_string(),
+            location: *location,
+          });
+        };
+        if arguments.len() != declared_variant.fields.len() {
+          return Err(RuntimeError::IncorrectArgumentCount {
+            function_name: format!("{enumeration}::{variant}"),
+            expected: declared_variant.fields.len(),
+            received: arguments.len(),
+            location: *location,
+          });
+        }
+
+        let payload = arguments
+          .iter()
+          .map(|argument| argument.eval(scope))
+          .collect::<RuntimeResult<_>>()?;
+        Ok(Variable::Enum(EnumValue {
+          enumeration: *enumeration,
+          variant: *variant,
+          payload,
+        }))
+      }
+      Expression::Field { .. } | Expression::Index { .. } => self.get_within(scope, Box::new(|variable| Ok(variable))),
       Expression::Operation {
         operator,
         left,
         right,
         location,
       } => operator.eval(scope, *location, left, right),
+      Expression::Unary {
+        operator, expression, ..
+      } => operator.eval_unary(scope, expression),
+      Expression::Function(function) => Ok(Variable::Function(Closure {
+        function: (**function).clone(),
+        environment: scope.capture(),
+      })),
       Expression::Call {
         function,
         arguments,
         location,
       } => {
-        if let Some(std_value) = std_call(*function, scope, arguments)? {
-          return Ok(std_value);
+        if let Expression::Identifier(name, _) = **function {
+          if let Some(std_value) = std_call(name, scope, arguments)? {
+            return Ok(std_value);
+          }
         }
 
-        let function = scope.get(function, *location)?.try_into_function(*location)?.clone();
+        let callee = function.eval(scope)?;
+        let closure = callee.try_into_function(*location)?;
+        let function = &closure.function;
         if arguments.len() != function.arguments.len() {
           return Err(RuntimeError::IncorrectArgumentCount {
-            function_name: function.name.to_string(),
+            function_name: function
+              .name
+              .map_or_else(|| "anonymous".to_string(), |name| name.to_string()),
             expected: function.arguments.len(),
             received: arguments.len(),
             location: *location,
           });
         }
-        scope.push();
-        for (i, provided) in arguments.iter().enumerate() {
-          let expected = function.arguments[i];
-          let value = provided.eval(scope)?;
-          scope.set(expected, value);
+        // evaluated fully before binding any, so parameters can't shadow the caller's variables
+        let arguments = arguments
+          .iter()
+          .map(|argument| argument.eval(scope))
+          .collect::<RuntimeResult<Vec<_>>>()?;
+
+        // the function runs in the environment it was defined in, not the caller's
+        let mut environment = closure.environment.enter();
+        environment.push();
+        for (name, value) in function.arguments.iter().zip(arguments) {
+          environment.set(*name, value);
         }
 
-        let result = match Statement::eval_block(scope, &function.block)? {
-          StatementValue::Early(value) | StatementValue::End(value) => value,
-        };
-        scope.pop();
-        Ok(result)
+        match Statement::eval_block(&mut environment, &function.block)? {
+          StatementValue::Early(value) | StatementValue::End(value) => Ok(value),
+          StatementValue::Break | StatementValue::Continue => {
+            unreachable!("loop control outside a loop is rejected when parsing")
+          }
+        }
+      }
+    }
+  }
+
+  /// Assigns to a variable, a field of a record, an element of a list or a key of a map
+  pub fn assign(&self, scope: &mut ScopeStack<'a>, value: Variable<'a>) -> RuntimeResult<()> {
+    match self {
+      Expression::Identifier(name, location) => scope.assign(*name, value, *location),
+      Expression::Index { expression, index, .. } => {
+        let index_location = index.location();
+        let index = index.eval(scope)?;
+        expression.place(
+          scope,
+          Box::new(move |variable| variable.set_index(&index, value, index_location)),
+        )
+      }
+      expression => expression.place(
+        scope,
+        Box::new(move |variable| {
+          *variable = value;
+          Ok(())
+        }),
+      ),
+    }
+  }
+
+  /// A copy of the part of this expression's value chosen by `select`. Variables are borrowed rather than copied, so
+  /// indexing one only copies the element.
+  fn get_within<'s>(&'s self, scope: &mut ScopeStack<'a>, select: Select<'s, 'a>) -> RuntimeResult<Variable<'a>> {
+    match self {
+      Expression::Identifier(name, location) => scope.get_within(name, *location, select),
+      Expression::Index { expression, index, .. } => {
+        let index_location = index.location();
+        let index = index.eval(scope)?;
+        expression.get_within(
+          scope,
+          Box::new(move |variable| select(variable.index(&index, index_location)?)),
+        )
+      }
+      Expression::Field {
+        expression,
+        field,
+        location,
+      } => expression.get_within(
+        scope,
+        Box::new(move |variable| select(variable.field(field, *location)?)),
+      ),
+      expression => Ok(select(&expression.eval(scope)?)?.clone()),
+    }
+  }
+
+  /// Runs `update` on the existing variable, or element within one, which this refers to
+  fn place<'s>(&'s self, scope: &mut ScopeStack<'a>, update: Update<'s, 'a>) -> RuntimeResult<()> {
+    match self {
+      Expression::Identifier(name, location) => scope.update(name, *location, update),
+      Expression::Index { expression, index, .. } => {
+        let index_location = index.location();
+        let index = index.eval(scope)?;
+        expression.place(
+          scope,
+          Box::new(move |variable| update(variable.index_mut(&index, index_location)?)),
+        )
       }
+      Expression::Field {
+        expression,
+        field,
+        location,
+      } => expression.place(
+        scope,
+        Box::new(move |variable| update(variable.field_mut(field, *location)?)),
+      ),
+      expression => Err(RuntimeError::InvalidExpression {
+        expected: "identifier or index",
+        location: expression.location(),
+      }),
     }
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    ast::Location,
+    interpreter::test_util::value,
+    test_util::{POINT, SHAPE},
+  };
+
+  fn numbers(numbers: &[f64]) -> Variable<'static> {
+    Variable::List(numbers.iter().map(|number| Variable::Number(*number)).collect())
+  }
+
+  #[test]
+  fn list() {
+    assert_eq!(value("[1, 1 + 1, 3]"), Ok(numbers(&[1., 2., 3.])));
+    assert_eq!(
+      value(
+        "[1]x
+[4, 5] = x"
+      ),
+      Ok(Variable::Number(5.))
+    );
+    assert_eq!(value("[1][0][[1, 2]]"), Ok(Variable::Number(2.)));
+  }
+
+  #[test]
+  fn index_assign() {
+    assert_eq!(
+      value(
+        "x
+9 = [1]x
+[4, 5] = x"
+      ),
+      Ok(numbers(&[4., 9.]))
+    );
+    assert_eq!(
+      value(
+        "x
+9 = [0][1]x
+[4, [5]] = x"
+      ),
+      Ok(Variable::List(vec![Variable::Number(4.), numbers(&[9.])]))
+    );
+  }
+
+  #[test]
+  fn index_out_of_range() {
+    assert_eq!(
+      value(
+        "[2]x
+[4, 5] = x"
+      ),
+      Err(RuntimeError::IndexOutOfRange {
+        index: 2,
+        length: 2,
+        location: Location::new(Some(1)),
+      })
+    );
+    assert_eq!(
+      value(
+        "9 = [-1]x
+[] = x"
+      ),
+      Err(RuntimeError::IndexOutOfRange {
+        index: -1,
+        length: 0,
+        location: Location::new(Some(5)),
+      })
+    );
+    assert_eq!(
+      value("[0.5][1]"),
+      Err(RuntimeError::InvalidType {
+        expected: "whole number",
+        location: Location::new(Some(1)),
+      })
+    );
+  }
+
+  #[test]
+  fn display_list() {
+    let list = value("[1, \"two \\\"2\\\"\", [true]]").unwrap();
+    assert_eq!(list.to_string(), "[1, \"two \\\"2\\\"\", [true]]");
+  }
+
+  #[test]
+  fn map() {
+    assert_eq!(value("[\"b\"]m\n{\"a\": 1, \"b\": 2} = m"), Ok(Variable::Number(2.)));
+    assert_eq!(
+      value("m\n3 = [\"c\"]m\n4 = [\"a\"]m\n{\"a\": 1} = m"),
+      value("{\"a\": 4, \"c\": 3}")
+    );
+    assert_eq!(
+      value("[\"b\"]{\"a\": 1}"),
+      Err(RuntimeError::MissingKey {
+        key: "b".to_string(),
+        location: Location::new(Some(1)),
+      })
+    );
+    assert_eq!(
+      value("{1: 1}"),
+      Err(RuntimeError::InvalidType {
+        expected: "string",
+        location: Location::new(Some(1)),
+      })
+    );
+  }
+
+  #[test]
+  fn display_map_round_trip() {
+    let code = "{\"b\": {\"c\": [1, \"x\"]}, \"a\": true}";
+    let map = value(code).unwrap();
+    let display = map.to_string();
+    assert_eq!(display, "{\"a\": true, \"b\": {\"c\": [1, \"x\"]}}");
+    assert_eq!(value(&display), Ok(map));
+  }
+
+  #[test]
+  fn display_escapes_round_trip() {
+    let list = value(r#"["\u{0}\u{1b}\"\\\n\r\t\u{7f}é", {"\u{1}": 1}]"#).unwrap();
+    let display = list.to_string();
+    assert_eq!(display, r#"["\u{0}\u{1b}\"\\\n\r\t\u{7f}é", {"\u{1}": 1}]"#);
+    assert_eq!(value(&display), Ok(list));
+  }
+
+  #[test]
+  fn display_non_finite_round_trip() {
+    let display = value("[0 / 1, 0 / -1, 0 / 0]").unwrap().to_string();
+    assert_eq!(display, "[0 / 1, 0 / -1, 0 / 0]");
+    let Ok(Variable::List(numbers)) = value(&display) else {
+      panic!("expected a list");
+    };
+    assert_eq!(
+      numbers[..2],
+      [Variable::Number(f64::INFINITY), Variable::Number(f64::NEG_INFINITY)]
+    );
+    assert!(matches!(numbers[2], Variable::Number(number) if number.is_nan()));
+  }
+
+  #[test]
+  fn record() {
+    let code = format!("p\n{{ y: 2, x: 1 }} Point new = p\n{POINT}");
+    let point = value(&code).unwrap();
+    assert_eq!(point.to_string(), "{x: 1, y: 2} Point new");
+    assert_eq!(value(&format!("{point}\n{POINT}")), Ok(point));
+
+    let code = format!("y.p\nx.p + 10 = y.p\n{{ x: 1, y: 2 }} Point new = p\n{POINT}");
+    assert_eq!(value(&code), Ok(Variable::Number(11.)));
+  }
+
+  #[test]
+  fn record_errors() {
+    assert_eq!(
+      value(&format!("z.{{ x: 1, y: 2 }} Point new\n{POINT}")),
+      Err(RuntimeError::UnknownField {
+        record: "Point".to_string(),
+        field: "z".to_string(),
+        location: Location::new(Some(1)),
+      })
+    );
+    assert_eq!(
+      value(&format!("{{ x: 1 }} Point new\n{POINT}")),
+      Err(RuntimeError::MissingField {
+        record: "Point".to_string(),
+        field: "y".to_string(),
+        location: Location::new(Some(9)),
+      })
+    );
+    assert_eq!(
+      value(&format!("{{ z: 1 }} Point new\n{POINT}")),
+      Err(RuntimeError::UnknownField {
+        record: "Point".to_string(),
+        field: "z".to_string(),
+        location: Location::new(Some(5)),
+      })
+    );
+    assert_eq!(
+      value("x.[1]"),
+      Err(RuntimeError::InvalidType {
+        expected: "record",
+        location: Location::new(Some(1)),
+      })
+    );
+  }
+
+  #[test]
+  fn variant() {
+    let code = format!("(2, \"3\")Rect::Shape\n{SHAPE}");
+    let rect = value(&code).unwrap();
+    assert_eq!(rect.to_string(), "(2, \"3\")Rect::Shape");
+    assert_eq!(value(&format!("{rect}\n{SHAPE}")), Ok(rect));
+    assert_eq!(
+      value(&format!("Empty::Shape\n{SHAPE}")).unwrap().to_string(),
+      "Empty::Shape"
+    );
+    assert_eq!(value(&format!("Shape\n{SHAPE}")).unwrap().to_string(), "Enum(Shape)");
+  }
+
+  #[test]
+  fn variant_errors() {
+    assert_eq!(
+      value(&format!("Square::Shape\n{SHAPE}")),
+      Err(RuntimeError::UnknownVariant {
+        enumeration: "Shape".to_string(),
+        variant: "Square".to_string(),
+        location: Location::new(Some(8)),
+      })
+    );
+    assert_eq!(
+      value(&format!("(1, 2)Circle::Shape\n{SHAPE}")),
+      Err(RuntimeError::IncorrectArgumentCount {
+        function_name: "Shape::Circle".to_string(),
+        expected: 1,
+        received: 2,
+        location: Location::new(Some(14)),
+      })
+    );
+  }
+
+  #[test]
+  fn anonymous_function() {
+    assert_eq!(
+      value(
+        "(4)double
+{ x * 2 }(x) fn = double"
+      ),
+      Ok(Variable::Number(8.))
+    );
+    assert_eq!(
+      value(
+        "(3, { x * 2 }(x) fn)apply
+        { (v)f return }(v, f) apply fn"
+      ),
+      Ok(Variable::Number(6.))
+    );
+    assert_eq!(value("{}() fn").unwrap().to_string(), "Function(anonymous)");
+    assert_eq!(
+      value("(1, 2)f\n{ x }(x) fn = f"),
+      Err(RuntimeError::IncorrectArgumentCount {
+        function_name: "anonymous".to_string(),
+        expected: 1,
+        received: 2,
+        location: Location::new(Some(6)),
+      })
+    );
+  }
+
+  #[test]
+  fn lexical_scope() {
+    // a function doesn't see the locals of whatever calls it
+    assert_eq!(
+      value(
+        "()outer
+        {
+          ()inner
+          1 = secret
+        }() outer fn
+        { secret return }() inner fn"
+      ),
+      Err(RuntimeError::UnknownVariable {
+        name: "secret".to_string(),
+        location: Location::new(Some(88)),
+      })
+    );
+    // but does see later changes to where it was defined
+    assert_eq!(
+      value(
+        "()f
+        2 = y
+        { y return }() f fn
+        1 = y"
+      ),
+      Ok(Variable::Number(2.))
+    );
+  }
+
+  #[test]
+  fn closure() {
+    assert_eq!(
+      value(
+        "(2)add
+        (5)adder = add
+        { { x + n return }(x) fn return }(n) adder fn"
+      ),
+      Ok(Variable::Number(7.))
+    );
+    // each call captures its own frame
+    assert_eq!(
+      value(
+        "()add_one + ()add_two * 10
+        (2)adder = add_two
+        (1)adder = add_one
+        { { n return }() fn return }(n) adder fn"
+      ),
+      Ok(Variable::Number(21.))
+    );
+  }
+
+  #[test]
+  fn call_expression() {
+    assert_eq!(
+      value(
+        "(2)(5)adder
+        { { x + n return }(x) fn return }(n) adder fn"
+      ),
+      Ok(Variable::Number(7.))
+    );
+    assert_eq!(value("(3)[0][{ x * 2 }(x) fn]"), Ok(Variable::Number(6.)));
+    assert_eq!(value("(1)({ x }(x) fn)"), Ok(Variable::Number(1.)));
+  }
+
+  #[test]
+  fn not_callable() {
+    assert_eq!(
+      value("()5"),
+      Err(RuntimeError::NotCallable {
+        type_name: "number",
+        location: Location::new(Some(2)),
+      })
+    );
+    assert_eq!(
+      value("()[0]x\n[\"a\"] = x"),
+      Err(RuntimeError::NotCallable {
+        type_name: "string",
+        location: Location::new(Some(4)),
+      })
+    );
+  }
+
+  #[test]
+  fn arguments_in_caller_scope() {
+    // `i` is bound to the caller's `n`, not the `n` parameter bound just before it
+    assert_eq!(
+      value(
+        "(n, i + 1)f
+        { i * 10 + n return }(i, n) f fn
+        5 = n
+        1 = i"
+      ),
+      Ok(Variable::Number(52.))
+    );
+    // a parameter of the same name as the argument's variable
+    assert_eq!(
+      value(
+        "(x, x * 2)f
+        { x * 10 + y return }(y, x) f fn
+        3 = x"
+      ),
+      Ok(Variable::Number(63.))
+    );
+    // nested calls of the same function
+    assert_eq!(
+      value(
+        "(n, (n, 1)f)f
+        { a * 10 + b return }(b, a) f fn
+        2 = n"
+      ),
+      Ok(Variable::Number(122.))
+    );
+  }
+}
@@ -1,6 +1,7 @@
-use super::{scope::ScopeStack, variable::Variable, RuntimeResult};
+use std::cmp::Ordering;
+
+use super::{scope::ScopeStack, variable::Variable, RuntimeError, RuntimeResult};
 use crate::ast::Location;
-use crate::interpreter::Eval;
 use crate::{ast::expression::Expression, token::Operator};
 
 impl Operator {
@@ -13,18 +14,16 @@ impl Operator {
   ) -> RuntimeResult<Variable<'a>> {
     let left_loc = left.location();
     let left = left.eval(scope)?;
-    match self {
-      Operator::Assign => {
-        scope.set(right.try_into_identifier()?, left);
-        return Ok(Variable::Nil);
-      }
-      _ => (),
+    if *self == Operator::Assign {
+      right.assign(scope, left)?;
+      return Ok(Variable::Nil);
     }
 
     let right_loc = right.location();
     let right = right.eval(scope)?;
     match self {
       Operator::Equals => Ok(Variable::Bool(left == right)),
+      Operator::NotEquals => Ok(Variable::Bool(left != right)),
       Operator::Divide => Ok(Variable::Number(
         right.try_into_number(right_loc)? / left.try_into_number(left_loc)?,
       )),
@@ -40,17 +39,19 @@ impl Operator {
       Operator::Modulo => Ok(Variable::Number(
         right.try_into_number(right_loc)? % left.try_into_number(left_loc)?,
       )),
-      Operator::Lte => Ok(Variable::Bool(
-        left.try_into_number(left_loc)? <= right.try_into_number(right_loc)?,
-      )),
-      Operator::Gte => Ok(Variable::Bool(
-        left.try_into_number(left_loc)? >= right.try_into_number(right_loc)?,
-      )),
+      Operator::Lte => Ok(Variable::Bool(matches!(
+        compare(&left, left_loc, &right, right_loc)?,
+        Some(Ordering::Less | Ordering::Equal)
+      ))),
+      Operator::Gte => Ok(Variable::Bool(matches!(
+        compare(&left, left_loc, &right, right_loc)?,
+        Some(Ordering::Greater | Ordering::Equal)
+      ))),
       Operator::Lt => Ok(Variable::Bool(
-        left.try_into_number(left_loc)? < right.try_into_number(right_loc)?,
+        compare(&left, left_loc, &right, right_loc)? == Some(Ordering::Less),
       )),
       Operator::Gt => Ok(Variable::Bool(
-        left.try_into_number(left_loc)? > right.try_into_number(right_loc)?,
+        compare(&left, left_loc, &right, right_loc)? == Some(Ordering::Greater),
       )),
       Operator::And => Ok(Variable::Bool(
         left.try_into_bool(left_loc)? && right.try_into_bool(right_loc)?,
@@ -58,7 +59,87 @@ impl Operator {
       Operator::Or => Ok(Variable::Bool(
         left.try_into_bool(left_loc)? || right.try_into_bool(right_loc)?,
       )),
-      Operator::Assign => unreachable!(),
+      Operator::Assign | Operator::Not => unreachable!(),
     }
   }
+
+  pub fn eval_unary<'a>(&self, scope: &mut ScopeStack<'a>, expression: &Expression<'a>) -> RuntimeResult<Variable<'a>> {
+    let location = expression.location();
+    let value = expression.eval(scope)?;
+    match self {
+      Operator::Not => Ok(Variable::Bool(!value.try_into_bool(location)?)),
+      Operator::Subtract => Ok(Variable::Number(-value.try_into_number(location)?)),
+      _ => unreachable!(),
+    }
+  }
+}
+
+/// Orders two numbers, or two strings lexicographically. `None` if either number is NaN.
+fn compare(
+  left: &Variable,
+  left_loc: Location,
+  right: &Variable,
+  right_loc: Location,
+) -> RuntimeResult<Option<Ordering>> {
+  match left {
+    Variable::Number(left) => Ok(left.partial_cmp(&right.try_into_number(right_loc)?)),
+    Variable::String(left) => Ok(Some(left.as_str().cmp(right.try_into_str(right_loc)?))),
+    _ => Err(RuntimeError::InvalidType {
+      expected: "number or string",
+      location: left_loc,
+    }),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::token::TokenStream;
+
+  fn eval(code: &str) -> RuntimeResult<Variable<'_>> {
+    let mut tokens = TokenStream::new(code).unwrap();
+    Expression::try_expression(&mut tokens)
+      .unwrap()
+      .eval(&mut ScopeStack::new())
+  }
+
+  #[test]
+  fn not_equals() {
+    assert_eq!(eval("1 != 2"), Ok(Variable::Bool(true)));
+    assert_eq!(eval("\"a\" != \"a\""), Ok(Variable::Bool(false)));
+    assert_eq!(eval("1 != \"1\""), Ok(Variable::Bool(true)));
+  }
+
+  #[test]
+  fn compare_strings() {
+    assert_eq!(eval("\"apple\" < \"banana\""), Ok(Variable::Bool(true)));
+    assert_eq!(eval("\"apple\" > \"Apple\""), Ok(Variable::Bool(true)));
+    assert_eq!(eval("\"b\" <= \"b\""), Ok(Variable::Bool(true)));
+    assert_eq!(eval("\"ab\" >= \"b\""), Ok(Variable::Bool(false)));
+  }
+
+  #[test]
+  fn compare_mismatched() {
+    assert_eq!(
+      eval("\"b\" < 1"),
+      Err(RuntimeError::InvalidType {
+        expected: "string",
+        location: Location::new(Some(6)),
+      })
+    );
+    assert_eq!(
+      eval("1 >= \"b\""),
+      Err(RuntimeError::InvalidType {
+        expected: "number",
+        location: Location::new(Some(5)),
+      })
+    );
+    assert_eq!(
+      eval("true > false"),
+      Err(RuntimeError::InvalidType {
+        expected: "number or string",
+        location: Location::new(Some(0)),
+      })
+    );
+  }
 }
@@ -1,4 +1,9 @@
-use std::collections::HashMap;
+use std::{
+  cell::RefCell,
+  collections::{HashMap, HashSet},
+  fmt,
+  rc::{Rc, Weak},
+};
 
 use crate::ast::{identifier::Identifier, Location};
 
@@ -6,21 +11,35 @@ use super::{variable::Variable, RuntimeError, RuntimeResult};
 
 struct Scope<'a> {
   pub variables: HashMap<Identifier<'a>, Variable<'a>>,
+  /// Variables which can't be reassigned
+  pub constants: HashSet<Identifier<'a>>,
 }
 
 impl<'a> Scope<'a> {
   fn new() -> Self {
     Scope {
       variables: HashMap::new(),
+      constants: HashSet::new(),
+    }
+  }
+
+  fn check_mutable(&self, name: &Identifier<'a>, location: Location) -> RuntimeResult<()> {
+    if self.constants.contains(name) {
+      Err(RuntimeError::AssignToConstant {
+        name: name.0.to_string(),
+        location,
+      })
+    } else {
+      Ok(())
     }
   }
 
   fn get(&self, name: &Identifier<'a>) -> Option<&Variable<'a>> {
-    self.variables.get(&name)
+    self.variables.get(name)
   }
 
   fn get_mut(&mut self, name: &Identifier<'a>) -> Option<&mut Variable<'a>> {
-    self.variables.get_mut(&name)
+    self.variables.get_mut(name)
   }
 
   fn set(&mut self, name: Identifier<'a>, variable: Variable<'a>) {
@@ -28,16 +47,39 @@ impl<'a> Scope<'a> {
   }
 }
 
-pub struct ScopeStack<'a>(Vec<Scope<'a>>);
+type Frame<'a> = Rc<RefCell<Scope<'a>>>;
+
+/// The frames visible from the code being run, innermost last. Frames are shared, so a function captures the
+/// environment it's defined in: it sees later changes to those frames, but not frames pushed afterwards.
+#[derive(Clone)]
+pub struct ScopeStack<'a>(Vec<Frame<'a>>);
 
 impl<'a> ScopeStack<'a> {
   pub fn new() -> Self {
-    ScopeStack(vec![Scope::new()])
+    ScopeStack(vec![Rc::new(RefCell::new(Scope::new()))])
   }
 
-  pub fn get(&self, name: &Identifier<'a>, location: Location) -> RuntimeResult<&Variable<'a>> {
+  /// The environment for a function defined in the innermost frame
+  pub fn capture(&self) -> Environment<'a> {
+    Environment(self.0.iter().cloned().map(Capture::Strong).collect())
+  }
+
+  /// A copy of the variable, as variables are always copied when read
+  pub fn get(&self, name: &Identifier<'a>, location: Location) -> RuntimeResult<Variable<'a>> {
+    self.get_within(name, location, |variable| Ok(variable))
+  }
+
+  /// A copy of part of the variable, such as an element of a list, chosen by `select` without copying the rest of it
+  pub fn get_within(
+    &self,
+    name: &Identifier<'a>,
+    location: Location,
+    select: impl for<'v> FnOnce(&'v Variable<'a>) -> RuntimeResult<&'v Variable<'a>>,
+  ) -> RuntimeResult<Variable<'a>> {
     for scope in self.0.iter().rev() {
-      if let Some(var) = scope.get(name) {
+      if let Some(var) = scope.borrow().get(name) {
+        let mut var = select(var)?.clone();
+        var.for_each_closure(&mut |closure| closure.environment.retain());
         return Ok(var);
       }
     }
@@ -48,10 +90,20 @@ impl<'a> ScopeStack<'a> {
     })
   }
 
-  pub fn get_mut(&mut self, name: &Identifier<'a>, location: Location) -> RuntimeResult<&mut Variable<'a>> {
-    for scope in self.0.iter_mut().rev() {
+  /// Modify the variable in place, in the innermost frame it's found in
+  pub fn update<R>(
+    &self,
+    name: &Identifier<'a>,
+    location: Location,
+    update: impl FnOnce(&mut Variable<'a>) -> RuntimeResult<R>,
+  ) -> RuntimeResult<R> {
+    for (i, scope) in self.0.iter().enumerate().rev() {
+      let mut scope = scope.borrow_mut();
+      scope.check_mutable(name, location)?;
       if let Some(var) = scope.get_mut(name) {
-        return Ok(var);
+        let result = update(var);
+        release(var, &self.0[..=i]);
+        return result;
       }
     }
 
@@ -61,15 +113,189 @@ impl<'a> ScopeStack<'a> {
     })
   }
 
-  pub fn set(&mut self, name: Identifier<'a>, variable: Variable<'a>) {
-    self.0.last_mut().unwrap().set(name, variable);
+  /// Declare the variable in the innermost frame, shadowing any outside it
+  pub fn set(&mut self, name: Identifier<'a>, mut variable: Variable<'a>) {
+    release(&mut variable, &self.0);
+    self.0.last().unwrap().borrow_mut().set(name, variable);
+  }
+
+  /// Declare the variable in the innermost frame, erroring if it would replace a constant declared there
+  pub fn declare(
+    &mut self,
+    name: Identifier<'a>,
+    mut variable: Variable<'a>,
+    constant: bool,
+    location: Location,
+  ) -> RuntimeResult<()> {
+    release(&mut variable, &self.0);
+    let mut scope = self.0.last().unwrap().borrow_mut();
+    scope.check_mutable(&name, location)?;
+    scope.set(name, variable);
+    if constant {
+      scope.constants.insert(name);
+    }
+    Ok(())
+  }
+
+  /// Update the variable in the innermost frame it's found in, or declare it if it isn't found
+  pub fn assign(&mut self, name: Identifier<'a>, mut variable: Variable<'a>, location: Location) -> RuntimeResult<()> {
+    for (i, scope) in self.0.iter().enumerate().rev() {
+      let mut scope = scope.borrow_mut();
+      if scope.get(&name).is_some() {
+        scope.check_mutable(&name, location)?;
+        release(&mut variable, &self.0[..=i]);
+        scope.set(name, variable);
+        return Ok(());
+      }
+    }
+    self.set(name, variable);
+    Ok(())
   }
 
   pub fn push(&mut self) {
-    self.0.push(Scope::new());
+    self.0.push(Rc::new(RefCell::new(Scope::new())));
   }
 
   pub fn pop(&mut self) {
     self.0.pop();
   }
 }
+
+/// Weaken the references functions within a variable have to the frame it's being stored in, and the frames outside
+/// that one. Those frames outlive the variable while it's stored, and it'd otherwise keep the frame storing it alive.
+fn release<'a>(variable: &mut Variable<'a>, frames: &[Frame<'a>]) {
+  variable.for_each_closure(&mut |closure| {
+    for capture in &mut closure.environment.0 {
+      if let Capture::Strong(frame) = capture {
+        if frames.iter().any(|outer| Rc::ptr_eq(outer, frame)) {
+          *capture = Capture::Weak(Rc::downgrade(frame));
+        }
+      }
+    }
+  });
+}
+
+#[derive(Clone)]
+enum Capture<'a> {
+  Strong(Frame<'a>),
+  /// A frame which stores the function, or is outside one that does
+  Weak(Weak<RefCell<Scope<'a>>>),
+}
+
+impl<'a> Capture<'a> {
+  /// The captured frame. A weak capture is only upgraded when reading the function out of the frame storing it, so
+  /// that frame is alive, and so are the frames outside it: they're either on the stack doing the read, or captured
+  /// by whatever is keeping that frame alive.
+  fn frame(&self) -> Frame<'a> {
+    match self {
+      Capture::Strong(frame) => frame.clone(),
+      Capture::Weak(frame) => frame
+        .upgrade()
+        .expect("the frame storing a function, and those outside it, outlive it"),
+    }
+  }
+
+  fn as_ptr(&self) -> *const RefCell<Scope<'a>> {
+    match self {
+      Capture::Strong(frame) => Rc::as_ptr(frame),
+      Capture::Weak(frame) => frame.as_ptr(),
+    }
+  }
+}
+
+/// The frames a function was defined in, innermost last.
+///
+/// A function stored in a frame it captured would form a cycle and never be freed, so stored functions only weakly
+/// capture the frames they're stored in. Reading a function out of a frame makes its captures strong again, so a copy
+/// that's returned or passed around keeps its environment alive. A function is only called, or copied anywhere else,
+/// after being read, so a weak capture is never used once its frame is gone.
+#[derive(Clone)]
+pub struct Environment<'a>(Vec<Capture<'a>>);
+
+impl<'a> Environment<'a> {
+  fn retain(&mut self) {
+    for capture in &mut self.0 {
+      *capture = Capture::Strong(capture.frame());
+    }
+  }
+
+  /// The stack for running a function in this environment
+  pub fn enter(&self) -> ScopeStack<'a> {
+    ScopeStack(self.0.iter().map(Capture::frame).collect())
+  }
+}
+
+impl fmt::Debug for Environment<'_> {
+  /// Only the number of frames, as a frame can contain a function which captured it
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "Environment({} frames)", self.0.len())
+  }
+}
+
+impl PartialEq for Environment<'_> {
+  /// Whether both captured the same frames
+  fn eq(&self, other: &Self) -> bool {
+    self.0.len() == other.0.len() && self.0.iter().zip(&other.0).all(|(a, b)| a.as_ptr() == b.as_ptr())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{ast::statement::Statement, interpreter::test_util::value, test_util::parse};
+
+  /// Runs the code in a frame of its own, returning whether that frame is still alive once it's popped
+  fn frame_kept(code: &str) -> bool {
+    let mut scope = ScopeStack::new();
+    scope.push();
+    let frame = Rc::downgrade(scope.0.last().unwrap());
+    Statement::eval_block(&mut scope, &parse(code).statements).unwrap();
+    scope.pop();
+    frame.upgrade().is_some()
+  }
+
+  #[test]
+  fn stored_function_frees_frame() {
+    assert!(!frame_kept("{ x }(x) fn = g"));
+    assert!(!frame_kept("[{ x }(x) fn] = fs"));
+    assert!(!frame_kept("{ 1 }() fn = [0]fs\n[0] = fs"));
+  }
+
+  #[test]
+  fn hoisted_function_frees_frame() {
+    assert!(!frame_kept("{ 1 }() inner fn"));
+    assert!(!frame_kept(
+      "(3)count
+      {
+        (1 - n)count return
+        { n return } n == 0 if
+      }(n) count fn"
+    ));
+  }
+
+  #[test]
+  fn stored_function_environment_alive() {
+    // stored outside the block it's defined in, which is gone by the time it's called
+    assert_eq!(
+      value(
+        "()g
+        { { n }() fn = g\n2 = n let } true if
+        g let"
+      ),
+      Ok(Variable::Number(2.))
+    );
+    // stored in an argument's frame, then returned and stored in a frame outside its own
+    assert_eq!(
+      value(
+        "()f
+        [0]fs = f
+        (3)wrap = fs
+        {
+          fs return
+          [{ n * 10 }() fn] = fs
+        }(n) wrap fn"
+      ),
+      Ok(Variable::Number(30.))
+    );
+  }
+}
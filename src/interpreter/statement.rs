@@ -1,21 +1,61 @@
 use crate::ast::{
-  statement::{conditional::Conditional, Statement},
+  expression::Expression,
+  statement::{
+    conditional::Conditional,
+    for_loop::{ForLoop, Iterable},
+    matching::{Match, Pattern},
+    while_loop::WhileLoop,
+    Statement,
+  },
   Location,
 };
 
-use super::{scope::ScopeStack, variable::Variable, Eval, RuntimeResult};
+use super::{
+  scope::ScopeStack,
+  variable::{Closure, Variable},
+  RuntimeError, RuntimeResult,
+};
 
 impl<'a> Statement<'a> {
   fn eval(&self, scope: &mut ScopeStack<'a>) -> RuntimeResult<StatementValue<'a>> {
     match self {
       Statement::Conditional(conditional) => conditional.eval(scope),
       Statement::Expression(expression) => Ok(StatementValue::End(expression.eval(scope)?)),
-      Statement::Function(function) => {
-        scope.set(function.name, Variable::Function(function.clone()));
+      Statement::Declaration(declaration) => {
+        let value = match &declaration.value {
+          Some(value) => value.eval(scope)?,
+          None => Variable::Nil,
+        };
+        scope.declare(declaration.name, value, declaration.constant, declaration.location)?;
+        Ok(StatementValue::End(Variable::Nil))
+      }
+      // declared by the enclosing block before it runs
+      Statement::Function(_) => Ok(StatementValue::End(Variable::Nil)),
+      Statement::Record(record) => {
+        scope.declare(
+          record.name,
+          Variable::RecordType(record.clone()),
+          false,
+          record.location,
+        )?;
         Ok(StatementValue::End(Variable::Nil))
       }
-      // this path only happens if it's the last statement, so it's fine anyway
+      Statement::Enum(enumeration) => {
+        scope.declare(
+          enumeration.name,
+          Variable::EnumType(enumeration.clone()),
+          false,
+          enumeration.location,
+        )?;
+        Ok(StatementValue::End(Variable::Nil))
+      }
+      Statement::Match(matching) => matching.eval(scope),
       Statement::Return(expression) => Ok(StatementValue::Early(expression.eval(scope)?)),
+      Statement::Defer(_) => unreachable!("deferred expressions are registered by the enclosing block"),
+      Statement::While(while_loop) => while_loop.eval(scope),
+      Statement::For(for_loop) => for_loop.eval(scope),
+      Statement::Break(_) => Ok(StatementValue::Break),
+      Statement::Continue(_) => Ok(StatementValue::Continue),
     }
   }
 }
@@ -23,46 +63,638 @@ impl<'a> Statement<'a> {
 pub enum StatementValue<'a> {
   Early(Variable<'a>),
   End(Variable<'a>),
+  /// A `break` or `continue`, which is passed up to the enclosing loop
+  Break,
+  Continue,
 }
 
 impl<'a> Statement<'a> {
   pub fn location(&self) -> Location {
     match self {
-      Statement::Conditional(conditional) => conditional.location,
+      Statement::Conditional(conditional) => conditional.location(),
       Statement::Expression(expression) => expression.location(),
+      Statement::Declaration(declaration) => declaration.location,
       Statement::Function(function) => function.location,
-      Statement::Return(ret) => ret.location(),
+      Statement::Record(record) => record.location,
+      Statement::Enum(enumeration) => enumeration.location,
+      Statement::Match(matching) => matching.location,
+      Statement::Return(ret) | Statement::Defer(ret) => ret.location(),
+      Statement::While(while_loop) => while_loop.location,
+      Statement::For(for_loop) => for_loop.location,
+      Statement::Break(location) | Statement::Continue(location) => *location,
     }
   }
 
+  /// Declares the block's functions, so they can be called before their declarations are reached. Then evaluates the
+  /// statements in order, then any deferred expressions in reverse, even if a statement errored.
   pub fn eval_block(scope: &mut ScopeStack<'a>, block: &[Statement<'a>]) -> RuntimeResult<StatementValue<'a>> {
-    let mut statements = block.iter().rev();
-    let last_statement = statements.next();
-    for statement in statements.rev() {
-      match statement {
-        Statement::Return(expression) => return Ok(StatementValue::Early(expression.eval(scope)?)),
-        statement => match statement.eval(scope)? {
-          StatementValue::Early(value) => return Ok(StatementValue::Early(value)),
-          _ => (),
-        },
+    for statement in block {
+      if let Statement::Function(function) = statement {
+        if let Some(name) = function.name {
+          let closure = Closure {
+            function: function.clone(),
+            environment: scope.capture(),
+          };
+          scope.declare(name, Variable::Function(closure), !function.mutable, function.location)?;
+        }
+      }
+    }
+
+    let mut deferred = Vec::new();
+    let mut result = Statement::eval_statements(scope, block, &mut deferred);
+    for expression in deferred.into_iter().rev() {
+      let deferred_result = expression.eval(scope);
+      if result.is_ok() {
+        // keep the first error, as later ones are likely caused by it
+        if let Err(err) = deferred_result {
+          result = Err(err);
+        }
       }
     }
+    result
+  }
 
-    if let Some(last_statement) = last_statement {
-      Ok(last_statement.eval(scope)?)
-    } else {
-      Ok(StatementValue::End(Variable::Nil))
+  /// Evaluates the block in its own scope, so variables declared within it don't outlive it
+  fn eval_scoped_block(scope: &mut ScopeStack<'a>, block: &[Statement<'a>]) -> RuntimeResult<StatementValue<'a>> {
+    scope.push();
+    let result = Statement::eval_block(scope, block);
+    scope.pop();
+    result
+  }
+
+  fn eval_statements<'b>(
+    scope: &mut ScopeStack<'a>,
+    block: &'b [Statement<'a>],
+    deferred: &mut Vec<&'b Expression<'a>>,
+  ) -> RuntimeResult<StatementValue<'a>> {
+    let mut value = StatementValue::End(Variable::Nil);
+    for statement in block {
+      value = match statement {
+        Statement::Defer(expression) => {
+          deferred.push(expression);
+          StatementValue::End(Variable::Nil)
+        }
+        statement => statement.eval(scope)?,
+      };
+      if !matches!(value, StatementValue::End(_)) {
+        return Ok(value);
+      }
     }
+    Ok(value)
   }
 }
 
 impl<'a> Conditional<'a> {
   fn eval(&self, scope: &mut ScopeStack<'a>) -> RuntimeResult<StatementValue<'a>> {
-    let condition = self.condition.eval(scope)?.try_into_bool(self.condition.location())?;
-    if condition {
-      Statement::eval_block(scope, &self.true_block)
-    } else {
-      Statement::eval_block(scope, &self.false_block)
+    for branch in &self.branches {
+      if branch.condition.eval(scope)?.try_into_bool(branch.location)? {
+        return Statement::eval_scoped_block(scope, &branch.block);
+      }
+    }
+    Statement::eval_scoped_block(scope, &self.else_block)
+  }
+}
+
+impl<'a> Match<'a> {
+  fn eval(&self, scope: &mut ScopeStack<'a>) -> RuntimeResult<StatementValue<'a>> {
+    let value = self.value.eval(scope)?;
+    for arm in &self.arms {
+      let bindings = match (&arm.pattern, &value) {
+        (Pattern::Wildcard, _) => Vec::new(),
+        (
+          Pattern::Variant {
+            enumeration,
+            variant,
+            bindings,
+          },
+          Variable::Enum(value),
+        ) if value.enumeration == *enumeration && value.variant == *variant => {
+          bindings.iter().copied().zip(value.payload.iter().cloned()).collect()
+        }
+        _ => continue,
+      };
+
+      // bindings live in their own scope, like a for loop's variable
+      scope.push();
+      for (name, variable) in bindings {
+        scope.set(name, variable);
+      }
+      let result = Statement::eval_block(scope, &arm.block);
+      scope.pop();
+      return result;
+    }
+
+    Err(RuntimeError::NoMatchingArm {
+      value: value.to_string(),
+      location: self.location,
+    })
+  }
+}
+
+impl<'a> WhileLoop<'a> {
+  fn eval(&self, scope: &mut ScopeStack<'a>) -> RuntimeResult<StatementValue<'a>> {
+    while self.condition.eval(scope)?.try_into_bool(self.location)? {
+      if let Some(value) = eval_loop_block(scope, &self.block)? {
+        return Ok(value);
+      }
+    }
+    Ok(StatementValue::End(Variable::Nil))
+  }
+}
+
+impl<'a> ForLoop<'a> {
+  fn eval(&self, scope: &mut ScopeStack<'a>) -> RuntimeResult<StatementValue<'a>> {
+    for value in self.iterable.eval(scope)? {
+      // each iteration binds the loop variable in a new scope, so functions defined in it capture that iteration's value
+      scope.push();
+      scope.set(self.variable, value);
+      let result = eval_loop_block(scope, &self.block);
+      scope.pop();
+      if let Some(value) = result? {
+        return Ok(value);
+      }
     }
+    Ok(StatementValue::End(Variable::Nil))
+  }
+}
+
+impl<'a> Iterable<'a> {
+  /// The values to iterate over, which are evaluated once before the loop starts
+  fn eval(&self, scope: &mut ScopeStack<'a>) -> RuntimeResult<Box<dyn Iterator<Item = Variable<'a>> + 'a>> {
+    match self {
+      Iterable::Range { start, end, step } => {
+        let start = start.eval(scope)?.try_into_number(start.location())?;
+        let end = end.eval(scope)?.try_into_number(end.location())?;
+        let step = match step {
+          Some(step) => {
+            let location = step.location();
+            let step = step.eval(scope)?.try_into_number(location)?;
+            if step == 0. {
+              return Err(RuntimeError::ZeroStep { location });
+            }
+            step
+          }
+          None => 1.,
+        };
+        let numbers = std::iter::successors(Some(start), move |number| Some(number + step));
+        Ok(Box::new(
+          numbers
+            .take_while(move |number| if step > 0. { *number < end } else { *number > end })
+            .map(Variable::Number),
+        ))
+      }
+      Iterable::Expression(expression) => match expression.eval(scope)? {
+        Variable::List(list) => Ok(Box::new(list.into_iter())),
+        Variable::Map(map) => Ok(Box::new(map.into_keys().map(Variable::String))),
+        Variable::String(string) => {
+          let chars: Vec<_> = string.chars().map(|char| Variable::String(char.to_string())).collect();
+          Ok(Box::new(chars.into_iter()))
+        }
+        _ => Err(RuntimeError::InvalidType {
+          expected: "list, map or string",
+          location: expression.location(),
+        }),
+      },
+    }
+  }
+}
+
+/// Runs one iteration of a loop in its own scope, returning `Some` with the loop's value if it should stop
+fn eval_loop_block<'a>(
+  scope: &mut ScopeStack<'a>,
+  block: &[Statement<'a>],
+) -> RuntimeResult<Option<StatementValue<'a>>> {
+  match Statement::eval_scoped_block(scope, block)? {
+    StatementValue::Early(value) => Ok(Some(StatementValue::Early(value))),
+    StatementValue::Break => Ok(Some(StatementValue::End(Variable::Nil))),
+    StatementValue::End(_) | StatementValue::Continue => Ok(None),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    interpreter::{
+      test_util::{run, value},
+      RuntimeError,
+    },
+    test_util::SHAPE,
+  };
+
+  #[test]
+  fn defer_reverse_order() {
+    let (result, x) = run(
+      "x * 10 + 3 = x
+      x * 10 + 1 = x defer
+      x * 10 + 2 = x defer
+      0 = x",
+    );
+    assert_eq!(result, Ok(()));
+    assert_eq!(x, Variable::Number(312.));
+  }
+
+  #[test]
+  fn defer_early_exit() {
+    let (_, x) = run(
+      "x * 10 + 9 = x
+      1 return
+      x * 10 + 1 = x defer
+      0 = x",
+    );
+    assert_eq!(x, Variable::Number(1.));
+
+    let (_, x) = run(
+      "{
+        x * 10 + 9 = x
+        break
+        x * 10 + 1 = x defer
+      } true while
+      0 = x",
+    );
+    assert_eq!(x, Variable::Number(1.));
+  }
+
+  #[test]
+  fn defer_each_iteration() {
+    let (_, x) = run(
+      "{
+        i + 1 = i
+        x + 1 = x defer
+      } i < 3 while
+      0 = i
+      0 = x",
+    );
+    assert_eq!(x, Variable::Number(3.));
+  }
+
+  #[test]
+  fn defer_on_error() {
+    let (result, x) = run(
+      "(unknown)print
+      x * 10 + 1 = x defer
+      0 = x",
+    );
+    assert!(matches!(result, Err(RuntimeError::UnknownVariable { .. })));
+    assert_eq!(x, Variable::Number(1.));
+
+    let (result, _) = run(
+      "(unknown)print defer
+      0 = x",
+    );
+    assert!(matches!(result, Err(RuntimeError::UnknownVariable { .. })));
+  }
+
+  #[test]
+  fn while_ends() {
+    let (result, x) = run(
+      "{ x + 2 = x } x < 5 while
+      0 = x",
+    );
+    assert_eq!(result, Ok(()));
+    assert_eq!(x, Variable::Number(6.));
+
+    let (result, x) = run(
+      "{ 1 = x } false while
+      0 = x",
+    );
+    assert_eq!(result, Ok(()));
+    assert_eq!(x, Variable::Number(0.));
+  }
+
+  #[test]
+  fn while_continue() {
+    let (_, x) = run(
+      "{
+        x + i = x
+        { continue } 2 % i == 0 if
+        i + 1 = i
+      } i < 6 while
+      0 = i
+      0 = x",
+    );
+    assert_eq!(x, Variable::Number(9.));
+  }
+
+  #[test]
+  fn while_return() {
+    // returns from the enclosing function, not just the loop
+    assert_eq!(
+      value(
+        "(5)f
+        {
+          -1 return
+          {
+            { i return } i * i > n if
+            i + 1 = i
+          } true while
+          0 = i
+        }(n) f fn"
+      ),
+      Ok(Variable::Number(3.))
+    );
+  }
+
+  #[test]
+  fn for_range() {
+    assert_eq!(
+      value(
+        "{
+          { x return } i == 3 if
+          x * 10 + i = x
+        } 4..1 in i for
+        0 = x"
+      ),
+      Ok(Variable::Number(123.))
+    );
+    assert_eq!(
+      value(
+        "{
+          { x return } i == 1 if
+          x * 10 + i = x
+        } -1 step 0..5 in i for
+        0 = x"
+      ),
+      Ok(Variable::Number(54321.))
+    );
+    assert_eq!(
+      value(
+        "{
+          { x return } i == 7 if
+          x * 10 + i = x
+        } 3 step 8..1 in i for
+        0 = x"
+      ),
+      Ok(Variable::Number(147.))
+    );
+  }
+
+  #[test]
+  fn for_scope() {
+    assert_eq!(
+      value(
+        "i
+        {} 3..0 in i for"
+      ),
+      Err(RuntimeError::UnknownVariable {
+        name: "i".to_string(),
+        location: Location::new(Some(0)),
+      })
+    );
+  }
+
+  #[test]
+  fn for_string() {
+    assert_eq!(
+      value(
+        "{
+          { c return } c == \"b\" if
+        } \"abc\" in c for"
+      ),
+      Ok(Variable::String("b".to_string()))
+    );
+  }
+
+  #[test]
+  fn for_zero_step() {
+    assert_eq!(
+      value("{} 0 step 3..0 in i for"),
+      Err(RuntimeError::ZeroStep {
+        location: Location::new(Some(3))
+      })
+    );
+  }
+
+  #[test]
+  fn for_map_keys() {
+    assert_eq!(
+      value(
+        "{
+          { k return } [k]m == 2 if
+        } m in k for
+        {\"b\": 1, \"a\": 2} = m"
+      ),
+      Ok(Variable::String("a".to_string()))
+    );
+  }
+
+  #[test]
+  fn match_bindings() {
+    let code = |shape: &str| {
+      format!(
+        "{{
+          {{ 0 return }} Empty::Shape
+          {{ w * h return }} (w, h)Rect::Shape
+          {{ r * r * 3 return }} (r)Circle::Shape
+        }} s match
+        {shape} = s
+        {SHAPE}"
+      )
+    };
+    assert_eq!(value(&code("(2, 3)Rect::Shape")), Ok(Variable::Number(6.)));
+    assert_eq!(value(&code("(2)Circle::Shape")), Ok(Variable::Number(12.)));
+    assert_eq!(value(&code("Empty::Shape")), Ok(Variable::Number(0.)));
+  }
+
+  #[test]
+  fn match_wildcard() {
+    let code = |shape: &str| {
+      format!(
+        "{{
+          {{ 2 return }} _
+          {{ 1 return }} Empty::Shape
+        }} {shape} match
+        {SHAPE}"
+      )
+    };
+    assert_eq!(value(&code("Empty::Shape")), Ok(Variable::Number(1.)));
+    assert_eq!(value(&code("(1)Circle::Shape")), Ok(Variable::Number(2.)));
+    assert_eq!(value("{ { 1 return } _ } 5 match"), Ok(Variable::Number(1.)));
+  }
+
+  #[test]
+  fn match_no_arm() {
+    assert_eq!(
+      value(&format!(
+        "{{ {{}} (r)Circle::Shape {{}} (w, h)Rect::Shape {{}} Empty::Shape }} 5 match\n{SHAPE}"
+      )),
+      Err(RuntimeError::NoMatchingArm {
+        value: "5".to_string(),
+        location: Location::new(Some(61)),
+      })
+    );
+  }
+
+  #[test]
+  fn minus_starting_line() {
+    let (_, x) = run("x\n-y = x\n2 = y");
+    assert_eq!(x, Variable::Number(-2.));
+    assert_eq!(
+      value(
+        "()g
+        {
+          x
+          -x return
+          1 = x
+        }() g fn"
+      ),
+      Ok(Variable::Number(-1.))
+    );
+  }
+
+  #[test]
+  fn negative_literal_statements() {
+    assert_eq!(
+      value("[y, z]\n-1 = y\n-0x2 = z\n3 = w"),
+      Ok(Variable::List(vec![Variable::Number(-1.), Variable::Number(-2.)]))
+    );
+    assert_eq!(
+      value(
+        "()f
+        {
+          -1 return
+          0 = x
+        }() f fn"
+      ),
+      Ok(Variable::Number(-1.))
+    );
+  }
+
+  #[test]
+  fn block_scope() {
+    assert_eq!(
+      value(
+        "y
+        { 1 = y } true if"
+      ),
+      Err(RuntimeError::UnknownVariable {
+        name: "y".to_string(),
+        location: Location::new(Some(0)),
+      })
+    );
+    assert_eq!(
+      value(
+        "x
+        { 2 = x } true if
+        1 = x"
+      ),
+      Ok(Variable::Number(2.))
+    );
+    assert_eq!(
+      value(
+        "x
+        { 2 = x let } true if
+        1 = x"
+      ),
+      Ok(Variable::Number(1.))
+    );
+  }
+
+  #[test]
+  fn assign_outer_from_function() {
+    assert_eq!(
+      value(
+        "count
+        ()increment
+        ()increment
+        { count + 1 = count }() increment fn
+        0 = count"
+      ),
+      Ok(Variable::Number(2.))
+    );
+    assert_eq!(
+      value(
+        "count
+        ()f
+        { 5 = count let }() f fn
+        0 = count"
+      ),
+      Ok(Variable::Number(0.))
+    );
+  }
+
+  #[test]
+  fn loop_iteration_scope() {
+    assert_eq!(
+      value(
+        "[()[0]fs, ()[1]fs]
+        { { i return }() fn = [i]fs } 2..0 in i for
+        [0, 0] = fs"
+      ),
+      Ok(Variable::List(vec![Variable::Number(0.), Variable::Number(1.)]))
+    );
+  }
+
+  #[test]
+  fn assign_to_constant() {
+    assert_eq!(
+      value(
+        "()f
+        { 2 = x }() f fn
+        1 = x const"
+      ),
+      Err(RuntimeError::AssignToConstant {
+        name: "x".to_string(),
+        location: Location::new(Some(18)),
+      })
+    );
+    assert_eq!(
+      value(
+        "()f
+        { 2 = [0]x }() f fn
+        [1] = x const"
+      ),
+      Err(RuntimeError::AssignToConstant {
+        name: "x".to_string(),
+        location: Location::new(Some(21)),
+      })
+    );
+    assert_eq!(
+      value(
+        "()f
+        { 2 return }() fn = f
+        { 1 return }() f fn mut"
+      ),
+      Ok(Variable::Number(2.))
+    );
+  }
+
+  #[test]
+  fn hoisting() {
+    assert_eq!(
+      value(
+        "{ 1 return }() f fn
+        ()f return"
+      ),
+      Ok(Variable::Number(1.))
+    );
+    // mutually recursive, within a nested block
+    assert_eq!(
+      value(
+        "{
+          {
+            (1 - n)is_even return
+            { false return } n == 0 if
+          }(n) is_odd fn
+          {
+            (1 - n)is_odd return
+            { true return } n == 0 if
+          }(n) is_even fn
+          [(7)is_even, (4)is_even] return
+        } true if"
+      ),
+      Ok(Variable::List(vec![Variable::Bool(false), Variable::Bool(true)]))
+    );
+    // only within the block they're declared in
+    assert_eq!(
+      value(
+        "()f
+        { {}() f fn } true if"
+      ),
+      Err(RuntimeError::UnknownVariable {
+        name: "f".to_string(),
+        location: Location::new(Some(2)),
+      })
+    );
   }
 }
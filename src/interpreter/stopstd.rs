@@ -2,14 +2,14 @@
 
 use crate::ast::{expression::Expression, identifier::Identifier};
 
-use super::{scope::ScopeStack, variable::Variable, Eval, RuntimeResult};
+use super::{scope::ScopeStack, variable::Variable, RuntimeResult};
 
 /// Returns `Some` if it matched and called a standard library function, `None` if it didn't
 pub fn std_call<'a>(
   identifier: Identifier<'a>,
   scope: &mut ScopeStack<'a>,
   arguments: &[Expression<'a>],
-) -> RuntimeResult<Option<Variable<'static>>> {
+) -> RuntimeResult<Option<Variable<'a>>> {
   match identifier {
     Identifier("print") => Ok(Some(print(eval_arguments(scope, arguments)?))),
     _ => Ok(None),
@@ -20,7 +20,7 @@ fn eval_arguments<'a>(scope: &mut ScopeStack<'a>, arguments: &[Expression<'a>])
   arguments.iter().map(|expr| expr.eval(scope)).collect()
 }
 
-fn print(arguments: Vec<Variable<'_>>) -> Variable<'static> {
+fn print<'a>(arguments: Vec<Variable<'a>>) -> Variable<'a> {
   for argument in arguments {
     println!("{}", argument)
   }
@@ -0,0 +1,25 @@
+//! Helpers for running code in the interpreter's tests
+
+use crate::{
+  ast::{identifier::Identifier, statement::Statement, Location},
+  test_util::parse,
+};
+
+use super::{scope::ScopeStack, statement::StatementValue, variable::Variable, RuntimeResult};
+
+/// Runs the code, returning the value of the block
+pub fn value(code: &str) -> RuntimeResult<Variable<'_>> {
+  match Statement::eval_block(&mut ScopeStack::new(), &parse(code).statements)? {
+    StatementValue::End(value) | StatementValue::Early(value) => Ok(value),
+    StatementValue::Break | StatementValue::Continue => unreachable!(),
+  }
+}
+
+/// Runs the code, returning the result and the final value of `x`
+pub fn run(code: &str) -> (RuntimeResult<()>, Variable<'_>) {
+  let ast = parse(code);
+  let mut scope = ScopeStack::new();
+  let result = Statement::eval_block(&mut scope, &ast.statements).map(|_| ());
+  let x = scope.get(&Identifier("x"), Location::new(None)).unwrap();
+  (result, x)
+}
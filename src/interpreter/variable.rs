@@ -1,23 +1,191 @@
-use crate::ast::{statement::function::Function, Location};
-use derive_more::Display;
+use std::{collections::BTreeMap, fmt};
 
-use super::{RuntimeError, RuntimeResult};
+use crate::ast::{
+  identifier::Identifier,
+  statement::{enumeration::Enum, function::Function, record::Record},
+  Location,
+};
 
-#[derive(Debug, Clone, PartialEq, Display)]
+use super::{scope::Environment, RuntimeError, RuntimeResult};
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Variable<'a> {
   String(String),
   Number(f64),
   Bool(bool),
-  Function(Function<'a>),
+  Function(Closure<'a>),
+  List(Vec<Variable<'a>>),
+  Map(BTreeMap<String, Variable<'a>>),
+  /// A record declaration, which values are constructed from
+  RecordType(Record<'a>),
+  Record(RecordValue<'a>),
+  /// An enum declaration, which variants are constructed from
+  EnumType(Enum<'a>),
+  Enum(EnumValue<'a>),
   Nil,
 }
 
+/// A function along with the environment it was defined in, which it runs in when called
+#[derive(Debug, Clone, PartialEq)]
+pub struct Closure<'a> {
+  pub function: Function<'a>,
+  pub environment: Environment<'a>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnumValue<'a> {
+  pub enumeration: Identifier<'a>,
+  pub variant: Identifier<'a>,
+  /// The payload's fields in the order they're declared
+  pub payload: Vec<Variable<'a>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordValue<'a> {
+  pub record: Identifier<'a>,
+  /// Every field of the record, in the order they're declared
+  pub fields: Vec<(Identifier<'a>, Variable<'a>)>,
+}
+
+impl fmt::Display for Variable<'_> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Variable::String(string) => write!(f, "{string}"),
+      Variable::Number(number) => write!(f, "{number}"),
+      Variable::Bool(bool) => write!(f, "{bool}"),
+      Variable::Function(closure) => write!(f, "{}", closure.function),
+      // containers are written as literals, so strings within them are quoted
+      Variable::List(list) => {
+        f.write_str("[")?;
+        for (i, variable) in list.iter().enumerate() {
+          if i > 0 {
+            f.write_str(", ")?;
+          }
+          variable.fmt_literal(f)?;
+        }
+        f.write_str("]")
+      }
+      Variable::Map(map) => {
+        f.write_str("{")?;
+        for (i, (key, variable)) in map.iter().enumerate() {
+          if i > 0 {
+            f.write_str(", ")?;
+          }
+          write_string_literal(f, key)?;
+          f.write_str(": ")?;
+          variable.fmt_literal(f)?;
+        }
+        f.write_str("}")
+      }
+      Variable::RecordType(record) => write!(f, "{record}"),
+      Variable::Record(value) => {
+        f.write_str("{")?;
+        for (i, (field, variable)) in value.fields.iter().enumerate() {
+          if i > 0 {
+            f.write_str(", ")?;
+          }
+          write!(f, "{field}: ")?;
+          variable.fmt_literal(f)?;
+        }
+        write!(f, "}} {} new", value.record)
+      }
+      Variable::EnumType(enumeration) => write!(f, "{enumeration}"),
+      Variable::Enum(value) => {
+        if !value.payload.is_empty() {
+          f.write_str("(")?;
+          for (i, variable) in value.payload.iter().enumerate() {
+            if i > 0 {
+              f.write_str(", ")?;
+            }
+            variable.fmt_literal(f)?;
+          }
+          f.write_str(")")?;
+        }
+        write!(f, "{}::{}", value.variant, value.enumeration)
+      }
+      Variable::Nil => write!(f, "Nil"),
+    }
+  }
+}
+
+impl Variable<'_> {
+  /// Writes the variable as code which evaluates to it
+  fn fmt_literal(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Variable::String(string) => write_string_literal(f, string),
+      // there are no literals for these, so they're written as the division producing them
+      Variable::Number(number) if number.is_nan() => f.write_str("0 / 0"),
+      Variable::Number(number) if number.is_infinite() => {
+        // the right operand is divided by the left
+        f.write_str(if *number > 0. { "0 / 1" } else { "0 / -1" })
+      }
+      variable => write!(f, "{variable}"),
+    }
+  }
+}
+
+/// Writes the string quoted, using only the escapes the lexer understands
+fn write_string_literal(f: &mut fmt::Formatter<'_>, string: &str) -> fmt::Result {
+  f.write_str("\"")?;
+  for char in string.chars() {
+    match char {
+      '"' => f.write_str("\\\"")?,
+      '\\' => f.write_str("\\\\")?,
+      '\n' => f.write_str("\\n")?,
+      '\r' => f.write_str("\\r")?,
+      '\t' => f.write_str("\\t")?,
+      char if char.is_control() => write!(f, "\\u{{{:x}}}", char as u32)?,
+      char => write!(f, "{char}")?,
+    }
+  }
+  f.write_str("\"")
+}
+
 impl<'a> Variable<'a> {
-  pub fn try_into_function(&self, location: Location) -> RuntimeResult<&Function<'a>> {
+  /// Call `f` on every function within the variable, including those within containers
+  pub fn for_each_closure(&mut self, f: &mut impl FnMut(&mut Closure<'a>)) {
+    match self {
+      Variable::Function(closure) => f(closure),
+      Variable::List(list) | Variable::Enum(EnumValue { payload: list, .. }) => {
+        for variable in list {
+          variable.for_each_closure(f);
+        }
+      }
+      Variable::Map(map) => {
+        for variable in map.values_mut() {
+          variable.for_each_closure(f);
+        }
+      }
+      Variable::Record(value) => {
+        for (_, variable) in &mut value.fields {
+          variable.for_each_closure(f);
+        }
+      }
+      _ => {}
+    }
+  }
+
+  pub fn type_name(&self) -> &'static str {
+    match self {
+      Variable::String(_) => "string",
+      Variable::Number(_) => "number",
+      Variable::Bool(_) => "bool",
+      Variable::Function(_) => "function",
+      Variable::List(_) => "list",
+      Variable::Map(_) => "map",
+      Variable::RecordType(_) => "record type",
+      Variable::Record(_) => "record",
+      Variable::EnumType(_) => "enum type",
+      Variable::Enum(_) => "enum",
+      Variable::Nil => "nil",
+    }
+  }
+
+  pub fn try_into_function(&self, location: Location) -> RuntimeResult<&Closure<'a>> {
     match self {
       Variable::Function(func) => Ok(func),
-      _ => Err(RuntimeError::InvalidType {
-        expected: "function",
+      variable => Err(RuntimeError::NotCallable {
+        type_name: variable.type_name(),
         location,
       }),
     }
@@ -52,4 +220,135 @@ impl<'a> Variable<'a> {
       }),
     }
   }
+
+  pub fn try_into_record_type(&self, location: Location) -> RuntimeResult<&Record<'a>> {
+    match self {
+      Variable::RecordType(record) => Ok(record),
+      _ => Err(RuntimeError::InvalidType {
+        expected: "record type",
+        location,
+      }),
+    }
+  }
+
+  pub fn try_into_enum_type(&self, location: Location) -> RuntimeResult<&Enum<'a>> {
+    match self {
+      Variable::EnumType(enumeration) => Ok(enumeration),
+      _ => Err(RuntimeError::InvalidType {
+        expected: "enum type",
+        location,
+      }),
+    }
+  }
+
+  pub fn field(&self, field: &Identifier<'a>, location: Location) -> RuntimeResult<&Variable<'a>> {
+    match self {
+      Variable::Record(value) => value
+        .fields
+        .iter()
+        .find(|(name, _)| name == field)
+        .map(|(_, variable)| variable)
+        .ok_or_else(|| unknown_field(value.record, field, location)),
+      _ => Err(not_record(location)),
+    }
+  }
+
+  pub fn field_mut(&mut self, field: &Identifier<'a>, location: Location) -> RuntimeResult<&mut Variable<'a>> {
+    match self {
+      Variable::Record(value) => {
+        let record = value.record;
+        value
+          .fields
+          .iter_mut()
+          .find(|(name, _)| name == field)
+          .map(|(_, variable)| variable)
+          .ok_or_else(|| unknown_field(record, field, location))
+      }
+      _ => Err(not_record(location)),
+    }
+  }
+
+  /// The element at `index` of a list, or the value at the key `index` of a map, where `location` is that of the index
+  pub fn index(&self, index: &Variable<'a>, location: Location) -> RuntimeResult<&Variable<'a>> {
+    match self {
+      Variable::List(list) => Ok(&list[list_index(list, index, location)?]),
+      Variable::Map(map) => {
+        let key = index.try_into_str(location)?;
+        map.get(key).ok_or_else(|| missing_key(key, location))
+      }
+      _ => Err(not_indexable(location)),
+    }
+  }
+
+  pub fn index_mut(&mut self, index: &Variable<'a>, location: Location) -> RuntimeResult<&mut Variable<'a>> {
+    match self {
+      Variable::List(list) => {
+        let i = list_index(list, index, location)?;
+        Ok(&mut list[i])
+      }
+      Variable::Map(map) => {
+        let key = index.try_into_str(location)?;
+        map.get_mut(key).ok_or_else(|| missing_key(key, location))
+      }
+      _ => Err(not_indexable(location)),
+    }
+  }
+
+  /// Replaces the element at `index` of a list, or inserts the value at the key `index` of a map
+  pub fn set_index(&mut self, index: &Variable<'a>, value: Variable<'a>, location: Location) -> RuntimeResult<()> {
+    match self {
+      Variable::Map(map) => {
+        map.insert(index.try_into_str(location)?.to_string(), value);
+      }
+      variable => *variable.index_mut(index, location)? = value,
+    }
+    Ok(())
+  }
+}
+
+fn not_record(location: Location) -> RuntimeError {
+  RuntimeError::InvalidType {
+    expected: "record",
+    location,
+  }
+}
+
+pub(super) fn unknown_field(record: Identifier, field: &Identifier, location: Location) -> RuntimeError {
+  RuntimeError::UnknownField {
+    record: record.to_string(),
+    field: field.to_string(),
+    location,
+  }
+}
+
+fn not_indexable(location: Location) -> RuntimeError {
+  RuntimeError::InvalidType {
+    expected: "list or map",
+    location,
+  }
+}
+
+fn missing_key(key: &str, location: Location) -> RuntimeError {
+  RuntimeError::MissingKey {
+    key: key.to_string(),
+    location,
+  }
+}
+
+fn list_index(list: &[Variable], index: &Variable, location: Location) -> RuntimeResult<usize> {
+  let index = index.try_into_number(location)?;
+  if index.fract() != 0. {
+    return Err(RuntimeError::InvalidType {
+      expected: "whole number",
+      location,
+    });
+  }
+  usize::try_from(index as i64)
+    .ok()
+    .filter(|i| *i < list.len())
+    .ok_or(RuntimeError::IndexOutOfRange {
+      index: index as i64,
+      length: list.len(),
+      location,
+    })
 }
@@ -1,3 +1,7 @@
 pub mod ast;
+pub mod cst;
 pub mod interpreter;
 pub mod token;
+
+#[cfg(test)]
+mod test_util;
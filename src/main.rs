@@ -14,7 +14,7 @@ enum LocatedError {
 }
 
 fn run(code: &str) -> Result<(), LocatedError> {
-  let mut tokens = TokenStream::new(code);
+  let mut tokens = TokenStream::new(code).map_err(AstError::from)?;
   let ast = Ast::new(&mut tokens)?;
   interpret(ast)?;
   Ok(())
@@ -0,0 +1,14 @@
+//! Fixtures and helpers shared by tests across the crate
+
+use crate::{ast::Ast, token::TokenStream};
+
+/// A record with two fields
+pub const POINT: &str = "{ x, y } Point record";
+
+/// An enum with a variant without a payload, and variants with one and two fields
+pub const SHAPE: &str = "{ Empty, (w, h)Rect, (r)Circle } Shape enum";
+
+/// Parses the code, which must be valid
+pub fn parse(code: &str) -> Ast<'_> {
+  Ast::new(&mut TokenStream::new(code).unwrap()).unwrap()
+}
@@ -1,12 +1,44 @@
-use crate::ast::{identifier::Identifier, Location};
+use crate::{
+  ast::{identifier::Identifier, Location},
+  cst::SyntaxKind,
+};
 use thiserror::Error;
 
+use self::lexer::Lexer;
+
+pub mod lexer;
+mod number;
+
 #[derive(PartialEq, Eq, Error, Debug)]
-#[error("token error: {error}")]
+#[error("token error: {kind}")]
 pub struct TokenError {
-  error: String,
+  pub kind: TokenErrorKind,
   pub location: Location,
 }
+
+#[derive(PartialEq, Eq, Error, Debug, Clone)]
+pub enum TokenErrorKind {
+  #[error("expected {0}")]
+  Expected(String),
+  #[error("missing identifier")]
+  MissingIdentifier,
+  #[error("unexpected character '{0}'")]
+  UnexpectedCharacter(char),
+  #[error("invalid number '{text}', {reason}")]
+  InvalidNumber { text: String, reason: &'static str },
+  #[error("invalid identifier '{0}', identifiers must end with a letter or '_'")]
+  InvalidIdentifier(String),
+  #[error("string is never closed, missing a matching '\"'")]
+  UnterminatedString,
+  #[error("'{}' is never closed, missing a matching '{}'", .0.str(), .0.closing().str())]
+  UnclosedGrammar(Grammar),
+  #[error("block comment is never closed, missing a matching '\\*'")]
+  UnterminatedComment,
+  #[error("unknown escape sequence '\\{0}'")]
+  UnknownEscape(char),
+  #[error("invalid unicode escape sequence, expected '\\u{{..}}' containing a hexadecimal code point")]
+  InvalidUnicodeEscape,
+}
 pub type TokenResult<T> = Result<T, TokenError>;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -15,8 +47,14 @@ pub enum Grammar {
   CloseBracket,
   OpenCurly,
   CloseCurly,
+  OpenSquare,
+  CloseSquare,
   DoubleQuote,
   Comma,
+  Colon,
+  PathSeparator,
+  Dot,
+  Range,
 }
 
 impl Grammar {
@@ -27,15 +65,59 @@ impl Grammar {
       CloseBracket => ")",
       OpenCurly => "{",
       CloseCurly => "}",
+      OpenSquare => "[",
+      CloseSquare => "]",
       DoubleQuote => "\"",
       Comma => ",",
+      Colon => ":",
+      PathSeparator => "::",
+      Dot => ".",
+      Range => "..",
+    }
+  }
+
+  /// The grammar which closes this one. Given Stop is read backwards, `)` is closed by `(`.
+  fn closing(&self) -> Grammar {
+    use Grammar::*;
+    match self {
+      CloseBracket => OpenBracket,
+      CloseCurly => OpenCurly,
+      OpenBracket => CloseBracket,
+      OpenCurly => CloseCurly,
+      CloseSquare => OpenSquare,
+      OpenSquare => CloseSquare,
+      DoubleQuote => DoubleQuote,
+      Comma => Comma,
+      Colon => Colon,
+      PathSeparator => PathSeparator,
+      Dot => Dot,
+      Range => Range,
     }
   }
+
+  /// Grammar which forms a token by itself (i.e. excluding quotes)
+  pub fn grammars() -> &'static [Grammar] {
+    use Grammar::*;
+    &[
+      OpenBracket,
+      CloseBracket,
+      OpenCurly,
+      CloseCurly,
+      OpenSquare,
+      CloseSquare,
+      Comma,
+      Colon,
+      PathSeparator,
+      Dot,
+      Range,
+    ]
+  }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Operator {
   Equals,
+  NotEquals,
   Divide,
   Multiply,
   Add,
@@ -48,6 +130,7 @@ pub enum Operator {
   And,
   Or,
   Assign,
+  Not,
 }
 
 impl Operator {
@@ -55,6 +138,7 @@ impl Operator {
     use Operator::*;
     match self {
       Equals => "==",
+      NotEquals => "!=",
       Divide => "/",
       Multiply => "*",
       Add => "+",
@@ -67,15 +151,56 @@ impl Operator {
       And => "&&",
       Or => "||",
       Assign => "=",
+      Not => "!",
     }
   }
 
   pub fn operators() -> &'static [Operator] {
     use Operator::*;
     &[
-      Equals, Divide, Multiply, Add, Subtract, Modulo, Lte, Gte, Lt, Gt, And, Or, Assign,
+      Equals, NotEquals, Divide, Multiply, Add, Subtract, Modulo, Lte, Gte, Lt, Gt, And, Or, Assign, Not,
     ]
   }
+
+  /// How tightly the operator binds to its operands, higher binds tighter.
+  ///
+  /// | Precedence | Operators           | Associativity |
+  /// |------------|---------------------|---------------|
+  /// | 7          | unary `!` `-`       | n/a           |
+  /// | 6          | `*` `/` `%`         | left          |
+  /// | 5          | `+` `-`             | left          |
+  /// | 4          | `<` `<=` `>` `>=`   | left          |
+  /// | 3          | `==` `!=`           | left          |
+  /// | 2          | `&&`                | left          |
+  /// | 1          | `\|\|`              | left          |
+  /// | 0          | `=`                 | right         |
+  pub fn precedence(&self) -> u8 {
+    use Operator::*;
+    match self {
+      Not => 7,
+      Multiply | Divide | Modulo => 6,
+      Add | Subtract => 5,
+      Lt | Lte | Gt | Gte => 4,
+      Equals | NotEquals => 3,
+      And => 2,
+      Or => 1,
+      Assign => 0,
+    }
+  }
+
+  /// Associativity is in reading order, so as Stop is read right to left `a - b - c` groups as `(c - b) - a`
+  pub fn associativity(&self) -> Associativity {
+    match self {
+      Operator::Assign => Associativity::Right,
+      _ => Associativity::Left,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Associativity {
+  Left,
+  Right,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -86,6 +211,20 @@ pub enum Keyword {
   True,
   False,
   Return,
+  While,
+  Break,
+  Continue,
+  Defer,
+  For,
+  In,
+  Step,
+  Record,
+  New,
+  Enum,
+  Match,
+  Let,
+  Const,
+  Mut,
 }
 
 impl Keyword {
@@ -98,250 +237,329 @@ impl Keyword {
       True => "true",
       False => "false",
       Return => "return",
+      While => "while",
+      Break => "break",
+      Continue => "continue",
+      Defer => "defer",
+      For => "for",
+      In => "in",
+      Step => "step",
+      Record => "record",
+      New => "new",
+      Enum => "enum",
+      Match => "match",
+      Let => "let",
+      Const => "const",
+      Mut => "mut",
     }
   }
+
+  pub fn keywords() -> &'static [Keyword] {
+    use Keyword::*;
+    &[
+      If, Else, Fn, True, False, Return, While, Break, Continue, Defer, For, In, Step, Record, New, Enum, Match, Let,
+      Const, Mut,
+    ]
+  }
+}
+
+/// Byte range of a token within the source, `end` is exclusive
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+  pub start: usize,
+  pub end: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenKind<'a> {
+  Keyword(Keyword),
+  Operator(Operator),
+  Grammar(Grammar),
+  Identifier(Identifier<'a>),
+  /// The contents of a string with escape sequences decoded, excluding the quotes
+  String(String),
+  Number(f64),
+}
+
+impl TokenKind<'_> {
+  /// Whether an operand can start with this token in reading order, i.e. it can be the rightmost token of an operand
+  fn starts_operand(&self) -> bool {
+    matches!(
+      self,
+      TokenKind::Identifier(_)
+        | TokenKind::String(_)
+        | TokenKind::Number(_)
+        | TokenKind::Keyword(Keyword::True | Keyword::False)
+        | TokenKind::Grammar(Grammar::CloseBracket | Grammar::CloseSquare)
+    )
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token<'a> {
+  pub kind: TokenKind<'a>,
+  /// The source text of the token
+  pub text: &'a str,
+  pub span: Span,
+  /// The doc comments directly preceding the token in reading order (i.e. below it), in file order
+  pub docs: Vec<&'a str>,
+  /// The whitespace and comments read directly before the token (i.e. after it in the file), in file order. Only
+  /// kept if lexed losslessly.
+  pub trivia: Vec<Trivia<'a>>,
+  /// Whether a new line is read directly before the token (i.e. after it in the file), as it's the last on its line
+  pub line_end: bool,
 }
 
+impl<'a> Token<'a> {
+  pub fn location(&self) -> Location {
+    Location::new(Some(self.span.start))
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriviaKind {
+  Whitespace,
+  /// `\\` line comment
+  Comment,
+  /// `\\\` line comment
+  DocComment,
+  /// `\* *\` comment
+  BlockComment,
+}
+
+/// Source text which isn't part of a token, i.e. whitespace and comments
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trivia<'a> {
+  pub kind: TriviaKind,
+  pub text: &'a str,
+  pub span: Span,
+}
+
+/// Cursor over the lexed tokens, used by the parser
 pub struct TokenStream<'a> {
-  // position of the next character, starts at the final index. `None` if at the end of the string.
-  next_position: Option<usize>,
-  string: &'a str,
+  tokens: Vec<Token<'a>>,
+  // index of the next token to be read
+  next_index: usize,
+  // trivia before the first token in the file, which is read last
+  trailing_trivia: Vec<Trivia<'a>>,
+  // the tokens covered by each syntax node the parser has finished, only recorded if lossless
+  nodes: Option<Vec<NodeRange>>,
+}
+
+/// The range of token indices a syntax node covers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NodeRange {
+  pub kind: SyntaxKind,
+  pub start: usize,
+  pub end: usize,
 }
 
 impl<'a> TokenStream<'a> {
-  pub fn new(string: &'a str) -> Self {
+  pub fn new(string: &'a str) -> TokenResult<Self> {
+    Ok(TokenStream::from_tokens(Lexer::new(string).tokenize()?))
+  }
+
+  /// A stream which keeps all trivia and records the syntax nodes parsed from it, so that a lossless syntax tree can
+  /// be built
+  pub fn new_lossless(string: &'a str) -> TokenResult<Self> {
+    let (tokens, trailing_trivia) = Lexer::lossless(string).tokenize_with_trivia()?;
+    Ok(TokenStream {
+      trailing_trivia,
+      nodes: Some(Vec::new()),
+      ..TokenStream::from_tokens(tokens)
+    })
+  }
+
+  /// Create a stream from tokens that are already in reading order
+  pub fn from_tokens(tokens: Vec<Token<'a>>) -> Self {
     TokenStream {
-      next_position: if string.is_empty() {
-        None
-      } else {
-        Some(string.len() - 1)
-      },
-      string,
+      tokens,
+      next_index: 0,
+      trailing_trivia: Vec::new(),
+      nodes: None,
     }
   }
 
-  pub fn location(&self) -> Location {
-    Location {
-      position: self.next_position,
-    }
+  /// Split the stream in to its tokens, the trivia before the first token in the file, and the recorded syntax nodes
+  /// (in the order they were finished)
+  pub fn into_parts(self) -> (Vec<Token<'a>>, Vec<Trivia<'a>>, Vec<NodeRange>) {
+    (self.tokens, self.trailing_trivia, self.nodes.unwrap_or_default())
   }
 
-  /// Peek at the next `n` character, if there are not `n` many characters left returns `None`
-  fn peek_next_n(&self, n: usize) -> Option<&'a str> {
-    self.next_position.and_then(|next_pos| {
-      if n <= next_pos + 1 {
-        self.string.get(next_pos + 1 - n..=next_pos)
-      } else {
-        None
-      }
-    })
+  /// Mark the start of a syntax node, to be passed to `finish_node` once it has been parsed
+  pub fn start_node(&self) -> usize {
+    self.next_index
   }
 
-  fn consume_next_n(&mut self, n: usize) -> Option<&'a str> {
-    if let Some(next_pos) = self.next_position {
-      if n <= next_pos + 1 {
-        let char = self
-          .string
-          .get(next_pos + 1 - n..=next_pos)
-          .expect("next_position should always be valid");
-        if next_pos >= n {
-          self.next_position = Some(next_pos - n);
-        } else {
-          self.next_position = None;
-        }
-        Some(char)
-      } else {
-        None
+  /// Record the tokens read since `start` as a syntax node, if lossless
+  pub fn finish_node(&mut self, kind: SyntaxKind, start: usize) {
+    if let Some(nodes) = &mut self.nodes {
+      if start < self.next_index {
+        nodes.push(NodeRange {
+          kind,
+          start,
+          end: self.next_index,
+        });
       }
-    } else {
-      None
     }
   }
 
-  fn peek_next_char(&self) -> Option<char> {
-    self.peek_next_n(1).and_then(|str| str.chars().nth(0))
-  }
-
-  fn consume_next_char(&mut self) -> Option<char> {
-    self.consume_next_n(1).and_then(|str| str.chars().nth(0))
-  }
-
-  // Skip any comments or whitespace
-  pub fn skip_noop(&mut self) {
-    loop {
-      if let Some(next_char) = self.peek_next_char() {
-        if next_char.is_whitespace() {
-          // consume the whitespace, then repeat.
-          self.consume_next_char();
-          return self.skip_noop();
-        } else if next_char == '\\' && self.peek_next_n(2) == Some("\\\\") {
-          // start of a comment, read until the end of the line
-          loop {
-            match self.consume_next_char() {
-              Some('\n') | None => break,
-              _ => continue,
-            }
-          }
-        } else {
-          break;
-        }
-      } else {
-        break;
-      }
-    }
+  pub fn tokens(&self) -> &[Token<'a>] {
+    &self.tokens
+  }
+
+  /// The location of the next token
+  pub fn location(&self) -> Location {
+    self.peek().map_or(Location::new(None), Token::location)
+  }
+
+  pub fn peek(&self) -> Option<&Token<'a>> {
+    self.peek_nth(0)
+  }
+
+  /// The token `n` tokens after the next one, without consuming anything
+  pub fn peek_nth(&self, n: usize) -> Option<&Token<'a>> {
+    self.tokens.get(self.next_index + n)
+  }
+
+  /// The doc comments of the next token joined by new lines, `None` if it has none
+  pub fn peek_docs(&self) -> Option<String> {
+    self
+      .peek()
+      .filter(|token| !token.docs.is_empty())
+      .map(|token| token.docs.join("\n"))
+  }
+
+  fn peek_kind(&self) -> Option<&TokenKind<'a>> {
+    self.peek().map(|token| &token.kind)
+  }
+
+  fn advance(&mut self) {
+    self.next_index += 1;
   }
 
   pub fn is_empty(&self) -> bool {
-    self.next_position.is_none()
-  }
-
-  pub fn try_identifier_opt(&mut self) -> TokenResult<Option<Identifier<'a>>> {
-    self.skip_noop();
-    for n in 1.. {
-      let str = match self.peek_next_n(n) {
-        Some(str) => str,
-        None => return Ok(None),
-      };
-
-      let char = str.chars().nth(0).unwrap();
-      if n == 1 {
-        if !Identifier::is_valid_first_char(char) {
-          return Err(TokenError {
-            error: format!("invalid first character '{char}' of identifier, must only be alphabetic or _",),
-            location: self.location(),
-          });
-        }
-      } else if !Identifier::is_valid_char(char) {
-        // end of identifier
-        return Ok(Some(Identifier(self.consume_next_n(n - 1).unwrap())));
+    self.next_index >= self.tokens.len()
+  }
+
+  pub fn try_identifier_opt(&mut self) -> Option<Identifier<'a>> {
+    match self.peek_kind() {
+      Some(TokenKind::Identifier(identifier)) => {
+        let identifier = *identifier;
+        self.advance();
+        Some(identifier)
       }
+      _ => None,
     }
-    unreachable!()
   }
 
   pub fn try_identifier(&mut self) -> TokenResult<Identifier<'a>> {
-    self.try_identifier_opt()?.ok_or_else(|| TokenError {
-      error: "missing identifier".to_string(),
+    self.try_identifier_opt().ok_or_else(|| TokenError {
+      kind: TokenErrorKind::MissingIdentifier,
       location: self.location(),
     })
   }
 
-  pub fn try_number_opt(&mut self) -> TokenResult<Option<f64>> {
-    self.skip_noop();
-    let mut had_decimal = false; // whether a decimal has already been seen
-    for n in 1.. {
-      match self.peek_next_n(n) {
-        Some(str) => {
-          let char = str.chars().nth(0).unwrap();
-          if n == 1 && !char.is_numeric() {
-            return Ok(None);
-          } else if char.is_numeric() {
-            continue;
-          } else if char == '.' {
-            if n == 1 {
-              return Err(TokenError {
-                error: "number cannot end in decimal".to_string(),
-                location: self.location(),
-              });
-            } else if had_decimal {
-              return Err(TokenError {
-                error: "invalid number, cannot have multiple decimals".to_string(),
-                location: self.location(),
-              });
-            } else {
-              had_decimal = true;
-            }
-            continue;
-          } else {
-            // non-number char, thus end of number
-          }
-        }
-        None if n == 1 => return Ok(None),
-        None => (), // end of file, thus end of number
-      };
-
-      // end of number
-      let number_str = self.consume_next_n(n - 1).unwrap();
-      return Ok(Some(
-        number_str
-          .parse()
-          .expect("number parsing restrictions should result in valid float"),
-      ));
+  pub fn try_number_opt(&mut self) -> Option<f64> {
+    match self.peek_kind() {
+      Some(TokenKind::Number(number)) => {
+        let number = *number;
+        self.advance();
+        Some(number)
+      }
+      _ => None,
     }
-    unreachable!()
   }
 
-  pub fn try_string_opt(&mut self) -> TokenResult<Option<&'a str>> {
-    self.skip_noop();
-
-    if self.try_chars(Grammar::DoubleQuote.str()).is_err() {
-      return Ok(None);
-    }
-    for n in 1.. {
-      let str = self.peek_next_n(n).ok_or_else(|| TokenError {
-        error: "expected string, found nothing".to_string(),
-        location: self.location(),
-      })?;
-
-      // TODO: unsure what an unfinished string will do here
-      let char = str.chars().nth(0).unwrap();
-      if char == Grammar::DoubleQuote.str().chars().next().unwrap() {
-        let inner_str = self.consume_next_n(n - 1).unwrap();
-        self.consume_next_char(); // consume the trailing "
-        return Ok(Some(inner_str));
+  pub fn try_string_opt(&mut self) -> Option<String> {
+    match self.peek_kind() {
+      Some(TokenKind::String(string)) => {
+        let string = string.clone();
+        self.advance();
+        Some(string)
       }
+      _ => None,
     }
-    unreachable!()
   }
 
-  fn try_chars<'b>(&mut self, str: &'b str) -> TokenResult<&'b str> {
-    self.skip_noop();
+  /// Consume the next token if it is a unary operator applying to the operand just read, which is either `!` or a `-`
+  /// with no operand before it to subtract from. That's decided by the token before it, not by spacing, so `a -b`
+  /// subtracts `b` like `a - b` does. As statements aren't delimited, a `-` starting a line always starts a new
+  /// operand rather than subtracting from the line above.
+  pub fn try_unary_operator_opt(&mut self) -> Option<Operator> {
+    let operator = self.peek_operator()?;
+    let unary = match operator {
+      Operator::Not => true,
+      Operator::Subtract => !self
+        .tokens
+        .get(self.next_index + 1)
+        .is_some_and(|before| before.kind.starts_operand() && !before.line_end),
+      _ => false,
+    };
+
+    if unary {
+      self.advance();
+      Some(operator)
+    } else {
+      None
+    }
+  }
+
+  /// The next token if it is an operator, without consuming it
+  pub fn peek_operator(&self) -> Option<Operator> {
+    match self.peek_kind() {
+      Some(TokenKind::Operator(operator)) => Some(*operator),
+      _ => None,
+    }
+  }
 
-    if self.peek_next_n(str.len()) == Some(str) {
-      self.consume_next_n(str.len());
-      Ok(str)
+  fn try_kind(&mut self, kind: TokenKind<'a>, expected: impl FnOnce() -> String) -> TokenResult<()> {
+    if self.peek_kind() == Some(&kind) {
+      self.advance();
+      Ok(())
     } else {
       Err(TokenError {
-        error: format!("expected: {str}"),
+        kind: TokenErrorKind::Expected(expected()),
         location: self.location(),
       })
     }
   }
 
   pub fn try_operator(&mut self, operator: Operator) -> TokenResult<Operator> {
-    self.try_chars(operator.str()).map(|_| operator)
+    self
+      .try_kind(TokenKind::Operator(operator), || format!("'{}'", operator.str()))
+      .map(|_| operator)
   }
 
   pub fn try_grammar(&mut self, grammar: Grammar) -> TokenResult<Grammar> {
-    self.try_chars(grammar.str()).map(|_| grammar)
+    self
+      .try_kind(TokenKind::Grammar(grammar), || format!("'{}'", grammar.str()))
+      .map(|_| grammar)
   }
 
-  pub fn try_keyword(&mut self, keyword: Keyword) -> TokenResult<Keyword> {
-    self.skip_noop();
-
-    if self.peek_next_n(keyword.str().len()) == Some(keyword.str()) {
-      // ensure the following character is not a valid identifier character
-      if let Some(after_char) = self
-        .peek_next_n(keyword.str().len() + 1)
-        .and_then(|str| str.chars().nth(0))
-      {
-        if Identifier::is_valid_char(after_char) {
-          return Err(TokenError {
-            error: format!("invalid keyword: {after_char}{}", keyword.str()),
-            location: self.location(),
-          });
-        }
-      }
-
-      self.consume_next_n(keyword.str().len());
-      Ok(keyword)
-    } else {
+  /// Returns an error pointing at the `open` grammar if the end of the file is reached before it is closed
+  pub fn check_unclosed(&self, open: Grammar, open_location: Location) -> TokenResult<()> {
+    if self.is_empty() {
       Err(TokenError {
-        error: format!("expected keyword '{}'", keyword.str()),
-        location: self.location(),
+        kind: TokenErrorKind::UnclosedGrammar(open),
+        location: open_location,
       })
+    } else {
+      Ok(())
     }
   }
+
+  /// Consume the grammar closing `open`, erroring at `open_location` if the end of the file has been reached
+  pub fn try_closing_grammar(&mut self, open: Grammar, open_location: Location) -> TokenResult<Grammar> {
+    self.check_unclosed(open, open_location)?;
+    self.try_grammar(open.closing())
+  }
+
+  pub fn try_keyword(&mut self, keyword: Keyword) -> TokenResult<Keyword> {
+    self
+      .try_kind(TokenKind::Keyword(keyword), || format!("keyword '{}'", keyword.str()))
+      .map(|_| keyword)
+  }
 }
 
 #[cfg(test)]
@@ -350,13 +568,13 @@ mod tests {
 
   #[test]
   fn keyword() {
-    let mut tokens = TokenStream::new(" if ");
+    let mut tokens = TokenStream::new(" if ").unwrap();
     assert_eq!(tokens.try_keyword(Keyword::If), Ok(Keyword::If));
   }
 
   #[test]
   fn grammar() {
-    let mut tokens = TokenStream::new(" () ");
+    let mut tokens = TokenStream::new(" () ").unwrap();
     assert!(tokens.try_grammar(Grammar::CloseCurly).is_err(),);
     assert_eq!(tokens.try_grammar(Grammar::CloseBracket), Ok(Grammar::CloseBracket));
     assert_eq!(tokens.try_grammar(Grammar::OpenBracket), Ok(Grammar::OpenBracket));
@@ -364,52 +582,62 @@ mod tests {
 
   #[test]
   fn operator() {
-    let mut tokens = TokenStream::new("+ == ");
+    let mut tokens = TokenStream::new("+ == ").unwrap();
     assert_eq!(tokens.try_operator(Operator::Equals), Ok(Operator::Equals));
+    assert_eq!(tokens.peek_operator(), Some(Operator::Add));
     assert_eq!(tokens.try_operator(Operator::Add), Ok(Operator::Add));
+    assert_eq!(tokens.peek_operator(), None);
   }
 
   #[test]
   fn string_opt() {
-    let mut tokens = TokenStream::new(" \"hello there\" ");
-    assert_eq!(tokens.try_string_opt(), Ok(Some("hello there")));
-    assert_eq!(tokens.try_string_opt(), Ok(None));
+    let mut tokens = TokenStream::new(" \"hello there\" ").unwrap();
+    assert_eq!(tokens.try_string_opt(), Some("hello there".to_string()));
+    assert_eq!(tokens.try_string_opt(), None);
   }
 
   #[test]
   fn number_opt() {
-    let mut tokens = TokenStream::new(" 1 33.01 55 ");
-    assert_eq!(tokens.try_number_opt(), Ok(Some(55.)));
-    assert_eq!(tokens.try_number_opt(), Ok(Some(33.01)));
-    assert_eq!(tokens.try_number_opt(), Ok(Some(1.)));
-    assert_eq!(tokens.try_number_opt(), Ok(None));
+    let mut tokens = TokenStream::new(" 1 33.01 55 ").unwrap();
+    assert_eq!(tokens.try_number_opt(), Some(55.));
+    assert_eq!(tokens.try_number_opt(), Some(33.01));
+    assert_eq!(tokens.try_number_opt(), Some(1.));
+    assert_eq!(tokens.try_number_opt(), None);
   }
 
   #[test]
   fn identifier_opt() {
-    let mut tokens = TokenStream::new("  2mY_var ");
-    assert_eq!(tokens.try_identifier_opt(), Ok(Some(Identifier("2mY_var"))));
-    assert_eq!(tokens.try_identifier_opt(), Ok(Some(Identifier("arg1"))));
-    assert_eq!(tokens.try_identifier_opt(), Ok(None));
+    let mut tokens = TokenStream::new(" 1arg 2mY_var ").unwrap();
+    assert_eq!(tokens.try_identifier_opt(), Some(Identifier("2mY_var")));
+    assert_eq!(tokens.try_identifier_opt(), Some(Identifier("1arg")));
+    assert_eq!(tokens.try_identifier_opt(), None);
   }
 
   #[test]
   fn identifier_invalid() {
-    let mut tokens = TokenStream::new(" var2 ");
-    assert!(tokens.try_identifier_opt().is_err());
+    assert!(TokenStream::new(" var2 ").is_err());
   }
 
   #[test]
   fn skip_noop() {
-    let mut tokens = TokenStream::new(
+    let tokens = TokenStream::new(
       " blah \\\\
     
       blah again\\\\
 
        ",
-    );
-    assert!(!tokens.is_empty());
-    tokens.skip_noop();
+    )
+    .unwrap();
     assert!(tokens.is_empty());
   }
+
+  #[test]
+  fn location() {
+    let mut tokens = TokenStream::new("a  b").unwrap();
+    assert_eq!(tokens.location(), Location::new(Some(3)));
+    tokens.try_identifier_opt();
+    assert_eq!(tokens.location(), Location::new(Some(0)));
+    tokens.try_identifier_opt();
+    assert_eq!(tokens.location(), Location::new(None));
+  }
 }
@@ -0,0 +1,638 @@
+use crate::ast::{identifier::Identifier, Location};
+
+use super::{
+  number::{has_radix_prefix, parse_number},
+  Grammar, Keyword, Operator, Span, Token, TokenError, TokenErrorKind, TokenKind, TokenResult, Trivia, TriviaKind,
+};
+
+/// Converts source text into a list of tokens in a single pass.
+///
+/// Stop is read from the bottom up (and right to left), so the lexer starts at the end of the source and the tokens it
+/// produces are in reading order, i.e. the last token in the file is the first token in the list.
+pub struct Lexer<'a> {
+  // byte offset of the end of the unread source, i.e. directly after the next character. Reading moves towards 0.
+  end: usize,
+  string: &'a str,
+  // whether to keep whitespace and comments on the tokens
+  lossless: bool,
+}
+
+impl<'a> Lexer<'a> {
+  pub fn new(string: &'a str) -> Self {
+    Lexer {
+      end: string.len(),
+      string,
+      lossless: false,
+    }
+  }
+
+  /// A lexer which keeps all whitespace and comments as trivia, so the source can be reproduced from the tokens
+  pub fn lossless(string: &'a str) -> Self {
+    Lexer {
+      lossless: true,
+      ..Lexer::new(string)
+    }
+  }
+
+  /// Lex the entire source, returning the tokens in reading order
+  pub fn tokenize(self) -> TokenResult<Vec<Token<'a>>> {
+    self.tokenize_with_trivia().map(|(tokens, _)| tokens)
+  }
+
+  /// Lex the entire source, also returning the trivia before the first token in the file (which is read last). Trivia
+  /// is only kept if the lexer is lossless.
+  pub fn tokenize_with_trivia(mut self) -> TokenResult<(Vec<Token<'a>>, Vec<Trivia<'a>>)> {
+    let mut tokens = Vec::new();
+    loop {
+      let mut trivia = self.skip_noop()?;
+      let line_end = trivia.iter().any(|trivia| trivia.text.contains('\n'));
+      if !self.lossless {
+        trivia.retain(|trivia| trivia.kind == TriviaKind::DocComment);
+      }
+      // trivia is read backwards, but kept in file order
+      trivia.reverse();
+
+      if self.unread().is_empty() {
+        return Ok((tokens, trivia));
+      }
+      tokens.push(self.next_token(trivia, line_end)?);
+    }
+  }
+
+  /// The location of the start of the next character
+  fn location(&self) -> Location {
+    Location::new(self.unread().char_indices().next_back().map(|(position, _)| position))
+  }
+
+  fn unread(&self) -> &'a str {
+    &self.string[..self.end]
+  }
+
+  /// Peek at the next `n` characters, if there are not `n` many characters left returns `None`
+  fn peek_next_n(&self, n: usize) -> Option<&'a str> {
+    if n == 0 {
+      return Some("");
+    }
+    let unread = self.unread();
+    unread
+      .char_indices()
+      .rev()
+      .nth(n - 1)
+      .map(|(start, _)| &unread[start..])
+  }
+
+  fn consume_next_n(&mut self, n: usize) -> Option<&'a str> {
+    let str = self.peek_next_n(n)?;
+    self.end -= str.len();
+    Some(str)
+  }
+
+  /// Peek at the character `n` characters ahead, where 0 is the next character
+  fn peek_nth_char(&self, n: usize) -> Option<char> {
+    self.unread().chars().rev().nth(n)
+  }
+
+  fn peek_next_char(&self) -> Option<char> {
+    self.peek_nth_char(0)
+  }
+
+  fn consume_next_char(&mut self) -> Option<char> {
+    let char = self.peek_next_char()?;
+    self.end -= char.len_utf8();
+    Some(char)
+  }
+
+  /// Skip any comments or whitespace, returning them as trivia in reading order
+  fn skip_noop(&mut self) -> TokenResult<Vec<Trivia<'a>>> {
+    let mut trivia = Vec::new();
+    while let Some(next_char) = self.peek_next_char() {
+      let end = self.end;
+      let kind = if next_char.is_whitespace() {
+        while self.peek_next_char().is_some_and(char::is_whitespace) {
+          self.consume_next_char();
+        }
+        TriviaKind::Whitespace
+      } else if self.peek_next_n(2) == Some("\\\\") {
+        // start of a comment, read until the end of the line. `\\\` is a doc comment, but not `\\\\`.
+        let is_doc = self.peek_next_n(3) == Some("\\\\\\") && self.peek_nth_char(3) != Some('\\');
+        while !matches!(self.peek_next_char(), Some('\n') | None) {
+          self.consume_next_char();
+        }
+        if is_doc {
+          TriviaKind::DocComment
+        } else {
+          TriviaKind::Comment
+        }
+      } else if self.peek_next_n(2) == Some("*\\") {
+        // start of a block comment, read until the closing `\*`
+        self.consume_next_n(2);
+        let location = Location::new(Some(self.end));
+        while self.peek_next_n(2) != Some("\\*") {
+          if self.consume_next_char().is_none() {
+            return Err(TokenError {
+              kind: TokenErrorKind::UnterminatedComment,
+              location,
+            });
+          }
+        }
+        self.consume_next_n(2);
+        TriviaKind::BlockComment
+      } else {
+        break;
+      };
+
+      trivia.push(Trivia {
+        kind,
+        text: &self.string[self.end..end],
+        span: Span { start: self.end, end },
+      });
+    }
+
+    Ok(trivia)
+  }
+
+  /// Lex the next token, which must exist. `trivia` is the trivia read before it, in file order.
+  fn next_token(&mut self, trivia: Vec<Trivia<'a>>, line_end: bool) -> TokenResult<Token<'a>> {
+    let next_char = self.peek_next_char().expect("there should be a next token");
+    let end = self.end;
+    let kind = if next_char.is_ascii_digit() {
+      self.lex_number()?
+    } else if Identifier::is_valid_first_char(next_char) {
+      self.lex_word()?
+    } else if self.peek_next_n(1) == Some(Grammar::DoubleQuote.str()) {
+      self.lex_string()?
+    } else if let Some(kind) = self.lex_symbol() {
+      kind
+    } else {
+      return Err(TokenError {
+        kind: TokenErrorKind::UnexpectedCharacter(next_char),
+        location: self.location(),
+      });
+    };
+
+    let docs = trivia
+      .iter()
+      .filter(|trivia| trivia.kind == TriviaKind::DocComment)
+      .map(|trivia| trivia.text.trim_end_matches('\\').trim())
+      .collect();
+    let start = self.end;
+    Ok(Token {
+      kind,
+      text: &self.string[start..end],
+      span: Span { start, end },
+      docs,
+      trivia: if self.lossless { trivia } else { Vec::new() },
+      line_end,
+    })
+  }
+
+  /// Lex an identifier or keyword
+  fn lex_word(&mut self) -> TokenResult<TokenKind<'a>> {
+    // step through the word once, rather than peeking at each char, as peeking `n` chars ahead walks all `n`
+    let len: usize = self
+      .unread()
+      .chars()
+      .rev()
+      .take_while(|char| Identifier::is_valid_char(*char))
+      .map(char::len_utf8)
+      .sum();
+    let word = &self.unread()[self.end - len..];
+    if has_radix_prefix(word) {
+      // hexadecimal can end in a letter, e.g. `0xff`
+      return self.lex_number();
+    }
+
+    self.end -= len;
+    Ok(match Keyword::keywords().iter().find(|keyword| keyword.str() == word) {
+      Some(keyword) => TokenKind::Keyword(*keyword),
+      None => TokenKind::Identifier(Identifier(word)),
+    })
+  }
+
+  fn lex_number(&mut self) -> TokenResult<TokenKind<'a>> {
+    // consume anything which could be part of a number, then validate it once it's known where it starts. The chars
+    // are stepped through once, looking at most two past the current one.
+    let mut chars = self.unread().chars().rev();
+    let mut len = 0;
+    while let Some(char) = chars.next() {
+      let mut ahead = chars.clone();
+      let part = match char {
+        // the first char is known to be part of it, and may be the letter ending a hexadecimal number
+        _ if len == 0 => true,
+        char if Identifier::is_valid_char(char) => true,
+        // a single `.` is a decimal
+        '.' => ahead.next() != Some('.'),
+        // the sign of an exponent, such as `1e-9`
+        '+' | '-' => {
+          matches!(ahead.next(), Some('e' | 'E'))
+            && ahead
+              .next()
+              .is_some_and(|char| char.is_ascii_digit() || char == '.' || char == '_')
+        }
+        _ => false,
+      };
+      if !part {
+        break;
+      }
+      len += char.len_utf8();
+    }
+
+    self.end -= len;
+    let number_str = &self.string[self.end..self.end + len];
+    parse_number(number_str)
+      .map(TokenKind::Number)
+      .map_err(|kind| TokenError {
+        kind,
+        location: Location::new(Some(self.end)),
+      })
+  }
+
+  fn lex_string(&mut self) -> TokenResult<TokenKind<'a>> {
+    // given strings are read backwards, the trailing " is the one that opens the string
+    let location = self.location();
+    let unterminated = TokenError {
+      kind: TokenErrorKind::UnterminatedString,
+      location,
+    };
+    if !self.count_backslashes_before_next().is_multiple_of(2) {
+      // the contents would escape the quote if read forwards
+      return Err(unterminated);
+    }
+    self.consume_next_char();
+    let end = self.end;
+    loop {
+      match self.peek_next_char() {
+        // a quote is escaped if it has an odd number of backslashes before it
+        Some('"') if self.count_backslashes_before_next().is_multiple_of(2) => {
+          let start = self.end;
+          self.consume_next_char(); // consume the leading "
+          return Ok(TokenKind::String(Lexer::unescape(&self.string[start..end], start)?));
+        }
+        Some(_) => {
+          self.consume_next_char();
+        }
+        None => return Err(unterminated),
+      }
+    }
+  }
+
+  /// The number of consecutive backslashes directly before (i.e. to the left of) the next character
+  fn count_backslashes_before_next(&self) -> usize {
+    self
+      .unread()
+      .chars()
+      .rev()
+      .skip(1)
+      .take_while(|char| *char == '\\')
+      .count()
+  }
+
+  /// Decode the escape sequences of a string's contents. `start` is the byte offset of the contents in the source.
+  ///
+  /// Escapes are read left to right (unlike everything else), so `"\n"` is still a newline.
+  fn unescape(str: &str, start: usize) -> TokenResult<String> {
+    let mut unescaped = String::with_capacity(str.len());
+    let mut chars = str.char_indices();
+    while let Some((index, char)) = chars.next() {
+      if char != '\\' {
+        unescaped.push(char);
+        continue;
+      }
+
+      let location = Location::new(Some(start + index));
+      let escaped = match chars.next() {
+        Some((_, '"')) => '"',
+        Some((_, '\\')) => '\\',
+        Some((_, 'n')) => '\n',
+        Some((_, 'r')) => '\r',
+        Some((_, 't')) => '\t',
+        Some((_, 'u')) => Lexer::unescape_unicode(&mut chars).ok_or(TokenError {
+          kind: TokenErrorKind::InvalidUnicodeEscape,
+          location,
+        })?,
+        Some((_, char)) => {
+          return Err(TokenError {
+            kind: TokenErrorKind::UnknownEscape(char),
+            location,
+          })
+        }
+        None => unreachable!("an odd number of trailing backslashes would escape the closing quote"),
+      };
+      unescaped.push(escaped);
+    }
+    Ok(unescaped)
+  }
+
+  /// Decode the `{..}` following a `\u`
+  fn unescape_unicode(chars: &mut std::str::CharIndices<'_>) -> Option<char> {
+    if chars.next()?.1 != '{' {
+      return None;
+    }
+
+    let mut code_point = 0;
+    let mut digits = 0;
+    loop {
+      match chars.next()?.1 {
+        '}' if digits > 0 => return char::from_u32(code_point),
+        char if digits < 6 => {
+          code_point = code_point * 16 + char.to_digit(16)?;
+          digits += 1;
+        }
+        _ => return None,
+      }
+    }
+  }
+
+  /// Lex an operator or grammar token, preferring the longest match
+  fn lex_symbol(&mut self) -> Option<TokenKind<'a>> {
+    let operators = Operator::operators()
+      .iter()
+      .map(|operator| (operator.str(), TokenKind::Operator(*operator)));
+    let grammars = Grammar::grammars()
+      .iter()
+      .map(|grammar| (grammar.str(), TokenKind::Grammar(*grammar)));
+
+    let (str, kind) = operators
+      .chain(grammars)
+      .filter(|(str, _)| self.peek_next_n(str.len()) == Some(*str))
+      .max_by_key(|(str, _)| str.len())?;
+    self.consume_next_n(str.len());
+    Some(kind)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn kinds(string: &str) -> Vec<TokenKind<'_>> {
+    Lexer::new(string)
+      .tokenize()
+      .unwrap()
+      .into_iter()
+      .map(|token| token.kind)
+      .collect()
+  }
+
+  #[test]
+  fn reading_order() {
+    assert_eq!(
+      kinds("(1arg, \"hi\")my_func"),
+      vec![
+        TokenKind::Identifier(Identifier("my_func")),
+        TokenKind::Grammar(Grammar::CloseBracket),
+        TokenKind::String("hi".to_string()),
+        TokenKind::Grammar(Grammar::Comma),
+        TokenKind::Identifier(Identifier("1arg")),
+        TokenKind::Grammar(Grammar::OpenBracket),
+      ]
+    );
+  }
+
+  #[test]
+  fn spans() {
+    let tokens = Lexer::new("1 + abc").tokenize().unwrap();
+    assert_eq!(tokens[0].text, "abc");
+    assert_eq!(tokens[0].span, Span { start: 4, end: 7 });
+    assert_eq!(tokens[1].span, Span { start: 2, end: 3 });
+    assert_eq!(tokens[2].span, Span { start: 0, end: 1 });
+  }
+
+  #[test]
+  fn keywords() {
+    assert_eq!(
+      kinds("else fn iff 2if"),
+      vec![
+        TokenKind::Identifier(Identifier("2if")),
+        TokenKind::Identifier(Identifier("iff")),
+        TokenKind::Keyword(Keyword::Fn),
+        TokenKind::Keyword(Keyword::Else),
+      ]
+    );
+  }
+
+  #[test]
+  fn operators() {
+    assert_eq!(
+      kinds("<= < = == != !"),
+      vec![
+        TokenKind::Operator(Operator::Not),
+        TokenKind::Operator(Operator::NotEquals),
+        TokenKind::Operator(Operator::Equals),
+        TokenKind::Operator(Operator::Assign),
+        TokenKind::Operator(Operator::Lt),
+        TokenKind::Operator(Operator::Lte),
+      ]
+    );
+  }
+
+  #[test]
+  fn number() {
+    assert_eq!(
+      kinds("1 33.01 55"),
+      vec![TokenKind::Number(55.), TokenKind::Number(33.01), TokenKind::Number(1.)]
+    );
+    assert!(Lexer::new("1.2.3").tokenize().is_err());
+  }
+
+  #[test]
+  fn number_forms() {
+    assert_eq!(
+      kinds("1e9 0xff 0b1010 1_000 2.5E-3"),
+      vec![
+        TokenKind::Number(0.0025),
+        TokenKind::Number(1000.),
+        TokenKind::Number(10.),
+        TokenKind::Number(255.),
+        TokenKind::Number(1e9)
+      ]
+    );
+    assert_eq!(
+      Lexer::new("1 + 0b12").tokenize(),
+      Err(TokenError {
+        kind: TokenErrorKind::InvalidNumber {
+          text: "0b12".to_string(),
+          reason: "expected only binary digits"
+        },
+        location: Location::new(Some(4)),
+      })
+    );
+  }
+
+  #[test]
+  fn negative_number() {
+    // the parser decides whether a `-` negates the number or subtracts it
+    assert_eq!(
+      kinds("-1 = a"),
+      vec![
+        TokenKind::Identifier(Identifier("a")),
+        TokenKind::Operator(Operator::Assign),
+        TokenKind::Number(1.),
+        TokenKind::Operator(Operator::Subtract),
+      ]
+    );
+    assert_eq!(
+      kinds("n-1e-9"),
+      vec![
+        TokenKind::Number(1e-9),
+        TokenKind::Operator(Operator::Subtract),
+        TokenKind::Identifier(Identifier("n")),
+      ]
+    );
+  }
+
+  #[test]
+  fn comments() {
+    assert_eq!(
+      kinds(
+        "blah \\\\
+        a
+        blah again \\\\"
+      ),
+      vec![TokenKind::Identifier(Identifier("a"))]
+    );
+  }
+
+  #[test]
+  fn utf8() {
+    let tokens = Lexer::new("\"héllo 👋\" = a\ncafé comment \\\\").tokenize().unwrap();
+    assert_eq!(tokens[0].kind, TokenKind::Identifier(Identifier("a")));
+    assert_eq!(tokens[2].kind, TokenKind::String("héllo 👋".to_string()));
+    assert_eq!(tokens[2].span, Span { start: 0, end: 13 });
+  }
+
+  #[test]
+  fn escapes() {
+    assert_eq!(
+      kinds(r#""say \"hi\"\n\t\\ \u{1F44B}\u{e9}""#),
+      vec![TokenKind::String("say \"hi\"\n\t\\ 👋é".to_string())]
+    );
+    assert_eq!(
+      kinds(r#""a\\" "\"""#),
+      vec![
+        TokenKind::String("\"".to_string()),
+        TokenKind::String("a\\".to_string())
+      ]
+    );
+  }
+
+  #[test]
+  fn invalid_escapes() {
+    assert_eq!(
+      Lexer::new(r#""ab\q""#).tokenize(),
+      Err(TokenError {
+        kind: TokenErrorKind::UnknownEscape('q'),
+        location: Location::new(Some(3)),
+      })
+    );
+    for invalid in [
+      r#""\u""#,
+      r#""\u{}""#,
+      r#""\u{zz}""#,
+      r#""\u{1234567}""#,
+      r#""\u{d800}""#,
+    ] {
+      assert_eq!(
+        Lexer::new(invalid).tokenize().map_err(|error| error.kind),
+        Err(TokenErrorKind::InvalidUnicodeEscape)
+      );
+    }
+  }
+
+  #[test]
+  fn unterminated_string() {
+    assert_eq!(
+      Lexer::new("(hi\")print").tokenize(),
+      Err(TokenError {
+        kind: TokenErrorKind::UnterminatedString,
+        location: Location::new(Some(3)),
+      })
+    );
+    assert_eq!(
+      Lexer::new(r#""a\""#).tokenize().map_err(|error| error.kind),
+      Err(TokenErrorKind::UnterminatedString)
+    );
+  }
+
+  #[test]
+  fn utf8_unexpected_character() {
+    let error = Lexer::new("a é").tokenize().unwrap_err();
+    assert_eq!(error.location, Location::new(Some(2)));
+  }
+
+  #[test]
+  fn block_comments() {
+    assert_eq!(
+      kinds(
+        r"a \* a comment
+        spanning *\ b \* lines *\ c"
+      ),
+      vec![
+        TokenKind::Identifier(Identifier("c")),
+        TokenKind::Identifier(Identifier("b")),
+        TokenKind::Identifier(Identifier("a")),
+      ]
+    );
+    assert_eq!(
+      Lexer::new(r"a never closed *\ b").tokenize(),
+      Err(TokenError {
+        kind: TokenErrorKind::UnterminatedComment,
+        location: Location::new(Some(15)),
+      })
+    );
+  }
+
+  #[test]
+  fn doc_comments() {
+    let tokens = Lexer::new(
+      r"a
+      b
+      first line \\\
+      not docs \\
+      second line \\\
+      c \\\\",
+    )
+    .tokenize()
+    .unwrap();
+    assert_eq!(tokens[0].kind, TokenKind::Identifier(Identifier("b")));
+    assert_eq!(tokens[0].docs, vec!["first line", "second line"]);
+    assert!(tokens[1].docs.is_empty());
+  }
+
+  #[test]
+  fn lossless() {
+    let string = "  a\n comment \\\\\n  \\* block *\\ b\n";
+    let (tokens, trivia) = Lexer::lossless(string).tokenize_with_trivia().unwrap();
+    assert_eq!(
+      tokens[0].trivia,
+      vec![Trivia {
+        kind: TriviaKind::Whitespace,
+        text: "\n",
+        span: Span { start: 31, end: 32 },
+      }]
+    );
+    assert_eq!(
+      tokens[1].trivia.iter().map(|trivia| trivia.kind).collect::<Vec<_>>(),
+      vec![
+        TriviaKind::Whitespace,
+        TriviaKind::Comment,
+        TriviaKind::Whitespace,
+        TriviaKind::BlockComment,
+        TriviaKind::Whitespace
+      ]
+    );
+    assert_eq!(trivia[0].text, "  ");
+
+    let mut reproduced: String = trivia.iter().map(|trivia| trivia.text).collect();
+    for token in tokens.iter().rev() {
+      reproduced.push_str(token.text);
+      reproduced.extend(token.trivia.iter().map(|trivia| trivia.text));
+    }
+    assert_eq!(reproduced, string);
+  }
+
+  #[test]
+  fn unexpected_character() {
+    assert!(Lexer::new("a $ b").tokenize().is_err());
+  }
+}
@@ -0,0 +1,156 @@
+//! Parsing of numeric literals, such as `1_000.5`, `1e-9`, `0xff` and `0b1010`
+
+use super::TokenErrorKind;
+
+/// Whether a word starts with a radix prefix (`0x` or `0b`), making it a number rather than an identifier
+pub fn has_radix_prefix(word: &str) -> bool {
+  word.starts_with("0x") || word.starts_with("0b")
+}
+
+/// Parse the text of a number literal. Negative numbers are parsed as a `-` applied to the literal.
+pub fn parse_number(text: &str) -> Result<f64, TokenErrorKind> {
+  if !text.starts_with(|char: char| char.is_ascii_digit()) {
+    // something like `var2`, which is an identifier that doesn't end in a valid character
+    return Err(TokenErrorKind::InvalidIdentifier(text.to_string()));
+  }
+
+  if let Some(digits) = text.strip_prefix("0x") {
+    parse_radix(digits, 16)
+  } else if let Some(digits) = text.strip_prefix("0b") {
+    parse_radix(digits, 2)
+  } else {
+    parse_decimal(text)
+  }
+  .map_err(|reason| TokenErrorKind::InvalidNumber {
+    text: text.to_string(),
+    reason,
+  })
+}
+
+/// Remove the `_` separators from digits, ensuring each one is between two digits
+fn strip_separators(digits: &str, radix: u32) -> Result<String, &'static str> {
+  let chars: Vec<char> = digits.chars().collect();
+  for (i, char) in chars.iter().enumerate() {
+    if *char == '_' {
+      let before = i.checked_sub(1).map(|i| chars[i]);
+      let after = chars.get(i + 1).copied();
+      if !(before.is_some_and(|char| char.is_digit(radix)) && after.is_some_and(|char| char.is_digit(radix))) {
+        return Err("digit separators must be between two digits");
+      }
+    }
+  }
+
+  let stripped = digits.replace('_', "");
+  if stripped.chars().all(|char| char.is_digit(radix)) {
+    Ok(stripped)
+  } else {
+    Err(match radix {
+      16 => "expected only hexadecimal digits",
+      2 => "expected only binary digits",
+      _ => "expected only digits",
+    })
+  }
+}
+
+fn parse_radix(digits: &str, radix: u32) -> Result<f64, &'static str> {
+  let digits = strip_separators(digits, radix)?;
+  if digits.is_empty() {
+    return Err("missing digits after the prefix");
+  }
+  u64::from_str_radix(&digits, radix)
+    .map(|value| value as f64)
+    .map_err(|_| "value is too large")
+}
+
+fn parse_decimal(text: &str) -> Result<f64, &'static str> {
+  let (mantissa, exponent) = match text.find(['e', 'E']) {
+    Some(index) => (&text[..index], Some(&text[index + 1..])),
+    None => (text, None),
+  };
+
+  let mut number = match mantissa.split_once('.') {
+    Some((integer, fraction)) => {
+      if fraction.contains('.') {
+        return Err("cannot have multiple decimals");
+      } else if fraction.is_empty() {
+        return Err("cannot end in a decimal");
+      }
+      format!("{}.{}", strip_separators(integer, 10)?, strip_separators(fraction, 10)?)
+    }
+    None => strip_separators(mantissa, 10)?,
+  };
+
+  if let Some(exponent) = exponent {
+    let (sign, digits) = match exponent.strip_prefix(['+', '-']) {
+      Some(digits) => (&exponent[..1], digits),
+      None => ("", exponent),
+    };
+    if digits.is_empty() {
+      return Err("missing digits in the exponent");
+    }
+    number = format!("{number}e{sign}{}", strip_separators(digits, 10)?);
+  }
+
+  Ok(
+    number
+      .parse()
+      .expect("number parsing restrictions should result in valid float"),
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn reason(text: &str) -> &'static str {
+    match parse_number(text) {
+      Err(TokenErrorKind::InvalidNumber { reason, .. }) => reason,
+      other => panic!("expected invalid number, got {other:?}"),
+    }
+  }
+
+  #[test]
+  fn decimal() {
+    assert_eq!(parse_number("42"), Ok(42.));
+    assert_eq!(parse_number("33.01"), Ok(33.01));
+    assert_eq!(parse_number("7.5"), Ok(7.5));
+    assert_eq!(parse_number("1_000_000"), Ok(1_000_000.));
+    assert_eq!(parse_number("1_0.0_1"), Ok(10.01));
+  }
+
+  #[test]
+  fn exponent() {
+    assert_eq!(parse_number("1e9"), Ok(1e9));
+    assert_eq!(parse_number("2.5E+3"), Ok(2500.));
+    assert_eq!(parse_number("1e-3"), Ok(0.001));
+    assert_eq!(reason("1e"), "missing digits in the exponent");
+    assert_eq!(reason("1e-"), "missing digits in the exponent");
+    assert_eq!(reason("1e5e3"), "expected only digits");
+  }
+
+  #[test]
+  fn radix() {
+    assert_eq!(parse_number("0xff"), Ok(255.));
+    assert_eq!(parse_number("0xFF_FF"), Ok(65535.));
+    assert_eq!(parse_number("0b1010"), Ok(10.));
+    assert_eq!(parse_number("0b1"), Ok(1.));
+    assert_eq!(reason("0x"), "missing digits after the prefix");
+    assert_eq!(reason("0xfg"), "expected only hexadecimal digits");
+    assert_eq!(reason("0b102"), "expected only binary digits");
+    assert_eq!(reason("0x1_0000_0000_0000_0000"), "value is too large");
+  }
+
+  #[test]
+  fn malformed() {
+    assert_eq!(reason("1.2.3"), "cannot have multiple decimals");
+    assert_eq!(reason("1."), "cannot end in a decimal");
+    assert_eq!(reason("1__0"), "digit separators must be between two digits");
+    assert_eq!(reason("1_"), "digit separators must be between two digits");
+    assert_eq!(reason("1_.5"), "digit separators must be between two digits");
+    assert_eq!(reason("0x_ff"), "digit separators must be between two digits");
+    assert_eq!(
+      parse_number("var2"),
+      Err(TokenErrorKind::InvalidIdentifier("var2".to_string()))
+    );
+  }
+}